@@ -1,19 +1,25 @@
 use crate::api::cloudflare::api_error::ApiError;
 use crate::api::cloudflare::api_result::ApiResult;
+use crate::api::cloudflare::result_info::ResultInfo;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct ApiResponseCollection<T: ApiResult> {
     result: Option<Vec<T>>,
     errors: Vec<ApiError>,
+    result_info: Option<ResultInfo>,
 }
 
 impl<T: ApiResult> ApiResponseCollection<T> {
+    pub const fn result_info(&self) -> Option<ResultInfo> {
+        self.result_info
+    }
+
     pub fn take_result(self) -> Option<Vec<T>> {
         self.result
     }
 
-    pub fn errors(&self) -> &Vec<ApiError> {
+    pub const fn errors(&self) -> &Vec<ApiError> {
         &self.errors
     }
 }
@@ -25,7 +31,11 @@ pub struct ApiResponseItem<T: ApiResult> {
 }
 
 impl<T: ApiResult> ApiResponseItem<T> {
-    pub fn errors(&self) -> &Vec<ApiError> {
+    pub fn take_result(self) -> Option<T> {
+        self.result
+    }
+
+    pub const fn errors(&self) -> &Vec<ApiError> {
         &self.errors
     }
 }
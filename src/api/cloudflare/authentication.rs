@@ -0,0 +1,19 @@
+use ureq::Request;
+
+/// The credentials sent with every request to the Cloudflare API.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Authentication {
+    /// A scoped API token, sent as a bearer token.
+    Token(String),
+    /// The legacy Global API Key, which must be accompanied by the account's email address.
+    Key { email: String, key: String },
+}
+
+impl Authentication {
+    pub fn apply(&self, request: Request) -> Request {
+        match self {
+            Self::Token(token) => request.set("authorization", &format!("Bearer {}", token)),
+            Self::Key { email, key } => request.set("x-auth-email", email).set("x-auth-key", key),
+        }
+    }
+}
@@ -1,25 +1,38 @@
 use crate::api::cloudflare::api_response::{ApiResponseCollection, ApiResponseItem};
+use crate::api::cloudflare::authentication::Authentication;
+use crate::api::cloudflare::deleted::Deleted;
 use crate::api::cloudflare::dns_record::DnsRecord;
+use crate::api::cloudflare::dns_record_patch::DnsRecordPatch;
 use crate::api::cloudflare::dns_record_type::DnsRecordType;
+use crate::api::cloudflare::result_info::ResultInfo;
 use crate::api::cloudflare::zone::Zone;
 use anyhow::Context;
-use std::net::IpAddr;
-use ureq::{json, Request, Response, SerdeValue};
+use ureq::serde_json::Value;
+use ureq::{json, Request, Response};
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Copy, Debug)]
 pub struct Client<'a> {
-    api_token: &'a str,
+    authentication: &'a Authentication,
     get_zone: fn(Request) -> Result<Response, ureq::Error>,
     get_dns_record: fn(Request) -> Result<Response, ureq::Error>,
-    patch_dns_record: fn(Request, SerdeValue) -> Result<Response, ureq::Error>,
+    patch_dns_record: fn(Request, Value) -> Result<Response, ureq::Error>,
+    post_dns_record: fn(Request, Value) -> Result<Response, ureq::Error>,
+    delete_dns_record: fn(Request) -> Result<Response, ureq::Error>,
 }
 
 impl<'a> Client<'a> {
-    pub fn new(api_token: &'a str) -> Self {
-        Self { api_token, get_zone: Self::get, get_dns_record: Self::get, patch_dns_record: Self::patch }
+    pub fn new(authentication: &'a Authentication) -> Self {
+        Self {
+            authentication,
+            get_zone: Self::get,
+            get_dns_record: Self::get,
+            patch_dns_record: Self::send,
+            post_dns_record: Self::send,
+            delete_dns_record: Self::get,
+        }
     }
 
-    // mocked
+    // mocked; also used for DELETE, which has no body
     #[cfg(not(tarpaulin_include))]
     fn get(request: Request) -> Result<Response, ureq::Error> {
         request.call()
@@ -27,15 +40,15 @@ impl<'a> Client<'a> {
 
     // mocked
     #[cfg(not(tarpaulin_include))]
-    fn patch(request: Request, json: SerdeValue) -> Result<Response, ureq::Error> {
+    fn send(request: Request, json: Value) -> Result<Response, ureq::Error> {
         request.send_json(json)
     }
 
     pub fn fetch_zone(&self, zone: &str) -> anyhow::Result<Zone> {
         let request = ureq::get("https://api.cloudflare.com/client/v4/zones")
             .query("name", zone)
-            .set("content-type", "application/json")
-            .set("authorization", &format!("Bearer {}", self.api_token));
+            .set("content-type", "application/json");
+        let request = self.authentication.apply(request);
 
         match (self.get_zone)(request) {
             Ok(response) | Err(ureq::Error::Status(_, response)) => {
@@ -77,20 +90,69 @@ impl<'a> Client<'a> {
         }
     }
 
-    pub fn fetch_dns_record(
+    pub fn fetch_zones(&self) -> anyhow::Result<Vec<Zone>> {
+        let mut zones = Vec::new();
+
+        for page in 1.. {
+            let request = ureq::get("https://api.cloudflare.com/client/v4/zones")
+                .query("page", &page.to_string())
+                .query("per_page", "50")
+                .set("content-type", "application/json");
+            let request = self.authentication.apply(request);
+
+            match (self.get_zone)(request) {
+                Ok(response) | Err(ureq::Error::Status(_, response)) => {
+                    let body: ApiResponseCollection<Zone> =
+                        response.into_json().context("failed to parse Zones JSON response")?;
+
+                    if !body.errors().is_empty() {
+                        if body.errors().len() > 1 {
+                            eprintln!("Errors returned from Zones API:");
+                            for error in body.errors() {
+                                eprintln!("- {}", error);
+                            }
+
+                            // cannot panic; only runs when body.errors.len() > 1
+                            anyhow::bail!(
+                                "Errors returned from Zones API; first one (see stderr for others): {}",
+                                body.errors()[0]
+                            );
+                        }
+
+                        // cannot panic; only runs when body.errors.len() >= 1
+                        anyhow::bail!("Error returned from Zones API: {}", body.errors()[0]);
+                    }
+
+                    let last_page = body.result_info().is_none_or(ResultInfo::is_last_page);
+                    zones.extend(body.take_result().context("Zone results is unexpectedly empty; should be a list")?);
+
+                    if last_page {
+                        break;
+                    }
+                }
+                Err(ureq::Error::Transport(e)) => {
+                    anyhow::bail!("transport error encountered when fetching Zones from API: {}", e)
+                }
+            }
+        }
+
+        Ok(zones)
+    }
+
+    pub fn fetch_dns_records(
         &self,
         zone_id: &str,
         dns_record: &str,
         dns_record_type: DnsRecordType,
-    ) -> anyhow::Result<DnsRecord> {
+    ) -> anyhow::Result<Vec<DnsRecord>> {
         let request = ureq::get(&format!(
             "https://api.cloudflare.com/client/v4/zones/{zone_identifier}/dns_records",
             zone_identifier = zone_id
         ))
         .query("name", dns_record)
         .query("type", &dns_record_type.to_string())
-        .set("content-type", "application/json")
-        .set("authorization", &format!("Bearer {}", self.api_token));
+        .set("content-type", "application/json");
+        let request = self.authentication.apply(request);
 
         match (self.get_dns_record)(request) {
             Ok(response) | Err(ureq::Error::Status(_, response)) => {
@@ -115,16 +177,7 @@ impl<'a> Client<'a> {
                     anyhow::bail!("Error returned from DNS Records API: {}", body.errors()[0]);
                 }
 
-                if let Some(mut result) = body.take_result() {
-                    if result.len() != 1 {
-                        anyhow::bail!("Unexpected number of DNS Records results; should be 1: {}", result.len());
-                    }
-
-                    // cannot panic; only runs when result.len() == 1
-                    Ok(result.swap_remove(0))
-                } else {
-                    anyhow::bail!("DNS Records results is unexpectedly empty; should be 1 result");
-                }
+                body.take_result().context("DNS Records results is unexpectedly empty; should be a list")
             }
             Err(ureq::Error::Transport(e)) => {
                 anyhow::bail!("transport error encountered when fetching Zones from API: {}", e)
@@ -132,7 +185,7 @@ impl<'a> Client<'a> {
         }
     }
 
-    pub fn update_dns_record(&self, zone_id: &str, dns_record_id: &str, ip: IpAddr) -> anyhow::Result<()> {
+    pub fn update_dns_record(&self, zone_id: &str, dns_record_id: &str, patch: &DnsRecordPatch) -> anyhow::Result<()> {
         let request = ureq::request(
             "PATCH",
             &format!(
@@ -141,10 +194,10 @@ impl<'a> Client<'a> {
                 identifier = dns_record_id
             ),
         )
-        .set("content-type", "application/json")
-        .set("authorization", &format!("Bearer {}", self.api_token));
+        .set("content-type", "application/json");
+        let request = self.authentication.apply(request);
 
-        match (self.patch_dns_record)(request, json!({ "content": ip })) {
+        match (self.patch_dns_record)(request, json!(patch)) {
             Ok(response) | Err(ureq::Error::Status(_, response)) => {
                 let body: ApiResponseItem<DnsRecord> =
                     response.into_json().context("failed to parse DNS Records update JSON response")?;
@@ -173,6 +226,99 @@ impl<'a> Client<'a> {
             }
         }
     }
+
+    pub fn create_dns_record(
+        &self,
+        zone_id: &str,
+        dns_record: &str,
+        dns_record_type: DnsRecordType,
+        patch: &DnsRecordPatch,
+    ) -> anyhow::Result<DnsRecord> {
+        let request = ureq::post(&format!(
+            "https://api.cloudflare.com/client/v4/zones/{zone_identifier}/dns_records",
+            zone_identifier = zone_id
+        ))
+        .set("content-type", "application/json");
+        let request = self.authentication.apply(request);
+
+        let mut body = json!(patch);
+        body["type"] = json!(dns_record_type.to_string());
+        body["name"] = json!(dns_record);
+
+        match (self.post_dns_record)(request, body) {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => {
+                let body: ApiResponseItem<DnsRecord> =
+                    response.into_json().context("failed to parse DNS Records create JSON response")?;
+
+                if !body.errors().is_empty() {
+                    if body.errors().len() > 1 {
+                        eprintln!("Errors returned from DNS Records create API:");
+                        for error in body.errors() {
+                            eprintln!("- {}", error);
+                        }
+
+                        // cannot panic; only runs when body.errors.len() > 1
+                        anyhow::bail!(
+                            "Errors returned from DNS Records create API; first one (see stderror for others): {}",
+                            body.errors()[0]
+                        );
+                    }
+                    // cannot panic; only runs when body.errors.len() >= 1
+                    anyhow::bail!("Error returned from DNS Records create API: {}", body.errors()[0]);
+                }
+
+                body.take_result().context("DNS Records create result is unexpectedly empty; should be 1 result")
+            }
+            Err(ureq::Error::Transport(e)) => {
+                anyhow::bail!("transport error encountered when creating DNS Record via API: {}", e)
+            }
+        }
+    }
+
+    pub fn delete_dns_record(&self, zone_id: &str, dns_record_id: &str) -> anyhow::Result<()> {
+        let request = ureq::delete(&format!(
+            "https://api.cloudflare.com/client/v4/zones/{zone_identifier}/dns_records/{identifier}",
+            zone_identifier = zone_id,
+            identifier = dns_record_id
+        ))
+        .set("content-type", "application/json");
+        let request = self.authentication.apply(request);
+
+        match (self.delete_dns_record)(request) {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => {
+                let body: ApiResponseItem<Deleted> =
+                    response.into_json().context("failed to parse DNS Records delete JSON response")?;
+
+                if !body.errors().is_empty() {
+                    if body.errors().len() > 1 {
+                        eprintln!("Errors returned from DNS Records delete API:");
+                        for error in body.errors() {
+                            eprintln!("- {}", error);
+                        }
+
+                        // cannot panic; only runs when body.errors.len() > 1
+                        anyhow::bail!(
+                            "Errors returned from DNS Records delete API; first one (see stderror for others): {}",
+                            body.errors()[0]
+                        );
+                    }
+                    // cannot panic; only runs when body.errors.len() >= 1
+                    anyhow::bail!("Error returned from DNS Records delete API: {}", body.errors()[0]);
+                }
+
+                match body.take_result() {
+                    Some(deleted) if deleted.id() == dns_record_id => Ok(()),
+                    Some(deleted) => {
+                        anyhow::bail!("DNS Records delete API deleted an unexpected record: {}", deleted.id())
+                    }
+                    None => anyhow::bail!("DNS Records delete result is unexpectedly empty; should be 1 result"),
+                }
+            }
+            Err(ureq::Error::Transport(e)) => {
+                anyhow::bail!("transport error encountered when deleting DNS Record via API: {}", e)
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -185,9 +331,17 @@ impl Client<'_> {
         self.get_dns_record = get_dns_record;
     }
 
-    pub fn set_patch_dns_record(&mut self, patch_dns_record: fn(Request, SerdeValue) -> Result<Response, ureq::Error>) {
+    pub fn set_patch_dns_record(&mut self, patch_dns_record: fn(Request, Value) -> Result<Response, ureq::Error>) {
         self.patch_dns_record = patch_dns_record;
     }
+
+    pub fn set_post_dns_record(&mut self, post_dns_record: fn(Request, Value) -> Result<Response, ureq::Error>) {
+        self.post_dns_record = post_dns_record;
+    }
+
+    pub fn set_delete_dns_record(&mut self, delete_dns_record: fn(Request) -> Result<Response, ureq::Error>) {
+        self.delete_dns_record = delete_dns_record;
+    }
 }
 
 #[cfg(test)]
@@ -195,38 +349,84 @@ pub mod tests {
     use std::net::{IpAddr, Ipv4Addr};
 
     use anyhow::Context;
-    use ureq::{Request, Response, SerdeValue};
+    use ureq::serde_json::Value;
+    use ureq::{Request, Response};
 
     use crate::api::cloudflare;
+    use crate::api::cloudflare::authentication::Authentication;
     use crate::api::cloudflare::dns_record::DnsRecord;
+    use crate::api::cloudflare::dns_record_patch::DnsRecordPatch;
     use crate::api::cloudflare::dns_record_type::DnsRecordType;
     use crate::api::cloudflare::zone::Zone;
 
     // Not an actual token; taken directly from the API documentation
     const API_TOKEN: &str = "YQSn-xWAQiiEh9qM58wZNnyQS7FUdoqGIUAbrh7T";
+    // Likewise for the Global API Key
+    const API_KEY: &str = "c2547eb745079dac9320b638f5e225cf483cc5cfdda41";
+    const EMAIL: &str = "user@example.com";
 
     const ZONE_ID: &str = "023e105f4ecef8ad9ca31a8372d0c353";
     const DNS_RECORD_ID: &str = "372e67954025e0ba6aaa6d586b9e0b59";
+    const DNS_RECORD_ID_2: &str = "4d0b2fa1e7a5e4a8c3d1f3b2e9a6c7d8";
 
     #[allow(non_snake_case)]
     fn ZONE() -> Zone {
-        Zone::new(ZONE_ID)
+        Zone::new(ZONE_ID, "example.com")
     }
 
     #[allow(non_snake_case)]
     fn DNS_RECORD() -> DnsRecord {
-        DnsRecord::new(DNS_RECORD_ID, false, IpAddr::V4(Ipv4Addr::new(198, 51, 100, 4)))
+        DnsRecord::new(DNS_RECORD_ID, false, IpAddr::V4(Ipv4Addr::new(198, 51, 100, 4)), 120, false, None)
     }
 
     pub fn mock_zone(_: Request) -> Result<Response, ureq::Error> {
         Response::new(200, "OK", include_str!("../../../resources/tests/cloudflare/zone.json"))
     }
 
+    /// Lists every zone, unless a specific zone is asked for by name.
+    pub fn mock_zones(request: Request) -> Result<Response, ureq::Error> {
+        if request.url().contains("name=") {
+            mock_zone(request)
+        } else if request.url().contains("page=2") {
+            Response::new(200, "OK", include_str!("../../../resources/tests/cloudflare/zones_2.json"))
+        } else {
+            Response::new(200, "OK", include_str!("../../../resources/tests/cloudflare/zones.json"))
+        }
+    }
+
+    /// Only succeeds if the request carries exactly one set of valid credentials.
+    fn mock_zone_authenticated(request: Request) -> Result<Response, ureq::Error> {
+        let bearer = format!("Bearer {}", API_TOKEN);
+        let headers = (request.header("authorization"), request.header("x-auth-email"), request.header("x-auth-key"));
+
+        match headers {
+            (Some(token), None, None) if token == bearer => mock_zone(request),
+            (None, Some(EMAIL), Some(API_KEY)) => mock_zone(request),
+            _ => mock_failure(request),
+        }
+    }
+
     pub fn mock_dns_record(_: Request) -> Result<Response, ureq::Error> {
         Response::new(200, "OK", include_str!("../../../resources/tests/cloudflare/dns_record.json"))
     }
 
-    pub fn mock_dns_record_update(_: Request, _: SerdeValue) -> Result<Response, ureq::Error> {
+    pub fn mock_dns_record_empty(_: Request) -> Result<Response, ureq::Error> {
+        Response::new(200, "OK", include_str!("../../../resources/tests/cloudflare/dns_record_empty.json"))
+    }
+
+    pub fn mock_dns_records_multiple(_: Request) -> Result<Response, ureq::Error> {
+        Response::new(200, "OK", include_str!("../../../resources/tests/cloudflare/dns_records_multiple.json"))
+    }
+
+    pub fn mock_dns_record_delete(_: Request) -> Result<Response, ureq::Error> {
+        Response::new(200, "OK", include_str!("../../../resources/tests/cloudflare/dns_record_delete.json"))
+    }
+
+    pub fn mock_dns_record_create(_: Request, _: Value) -> Result<Response, ureq::Error> {
+        Response::new(200, "OK", include_str!("../../../resources/tests/cloudflare/dns_record_create.json"))
+    }
+
+    pub fn mock_dns_record_update(_: Request, _: Value) -> Result<Response, ureq::Error> {
         Response::new(200, "OK", include_str!("../../../resources/tests/cloudflare/dns_record_update.json"))
     }
 
@@ -236,7 +436,8 @@ pub mod tests {
 
     #[test]
     fn fetch_zone() -> anyhow::Result<()> {
-        let mut client = cloudflare::client::Client::new(API_TOKEN);
+        let authentication = Authentication::Token(API_TOKEN.into());
+        let mut client = cloudflare::client::Client::new(&authentication);
         client.get_zone = mock_zone;
 
         assert_eq!(client.fetch_zone("example.com").context("failed to fetch mock Zone")?, ZONE());
@@ -245,15 +446,131 @@ pub mod tests {
     }
 
     #[test]
-    fn fetch_dns_record() -> anyhow::Result<()> {
-        let mut client = cloudflare::client::Client::new(API_TOKEN);
+    fn authentication() -> anyhow::Result<()> {
+        for authentication in
+            [Authentication::Token(API_TOKEN.into()), Authentication::Key { email: EMAIL.into(), key: API_KEY.into() }]
+        {
+            let mut client = cloudflare::client::Client::new(&authentication);
+            client.get_zone = mock_zone_authenticated;
+
+            assert_eq!(client.fetch_zone("example.com").context("failed to fetch mock Zone")?, ZONE());
+        }
+
+        let authentication = Authentication::Key { email: EMAIL.into(), key: API_TOKEN.into() };
+        let mut client = cloudflare::client::Client::new(&authentication);
+        client.get_zone = mock_zone_authenticated;
+
+        assert!(client.fetch_zone("example.com").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn fetch_zones() -> anyhow::Result<()> {
+        let authentication = Authentication::Token(API_TOKEN.into());
+        let mut client = cloudflare::client::Client::new(&authentication);
+        client.get_zone = mock_zones;
+
+        assert_eq!(
+            client.fetch_zones().context("failed to fetch mock Zones")?,
+            vec![
+                ZONE(),
+                Zone::new("9a7806061c88ada191ed06f989cc3dac", "example.co.uk"),
+                Zone::new("5d8e3f2a1b0c9d8e7f6a5b4c3d2e1f0a", "b.example.co.uk")
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn fetch_dns_records() -> anyhow::Result<()> {
+        let authentication = Authentication::Token(API_TOKEN.into());
+        let mut client = cloudflare::client::Client::new(&authentication);
         client.get_dns_record = mock_dns_record;
 
         assert_eq!(
             client
-                .fetch_dns_record(ZONE_ID, "example.com", DnsRecordType::A)
+                .fetch_dns_records(ZONE_ID, "example.com", DnsRecordType::A)
+                .context("failed to fetch mock DNS Record")?,
+            vec![DNS_RECORD()]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn fetch_dns_records_empty() -> anyhow::Result<()> {
+        let authentication = Authentication::Token(API_TOKEN.into());
+        let mut client = cloudflare::client::Client::new(&authentication);
+        client.get_dns_record = mock_dns_record_empty;
+
+        assert_eq!(
+            client
+                .fetch_dns_records(ZONE_ID, "example.com", DnsRecordType::A)
                 .context("failed to fetch mock DNS Record")?,
-            DNS_RECORD()
+            vec![]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn fetch_dns_records_multiple() -> anyhow::Result<()> {
+        let authentication = Authentication::Token(API_TOKEN.into());
+        let mut client = cloudflare::client::Client::new(&authentication);
+        client.get_dns_record = mock_dns_records_multiple;
+
+        assert_eq!(
+            client
+                .fetch_dns_records(ZONE_ID, "example.com", DnsRecordType::A)
+                .context("failed to fetch mock DNS Records")?,
+            vec![
+                DNS_RECORD(),
+                DnsRecord::new(
+                    DNS_RECORD_ID_2,
+                    false,
+                    IpAddr::V4(Ipv4Addr::new(198, 51, 100, 5)),
+                    120,
+                    false,
+                    Some("primary")
+                )
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn delete_dns_record() -> anyhow::Result<()> {
+        let authentication = Authentication::Token(API_TOKEN.into());
+        let mut client = cloudflare::client::Client::new(&authentication);
+        client.delete_dns_record = mock_dns_record_delete;
+
+        client.delete_dns_record(ZONE_ID, DNS_RECORD_ID_2).context("failed to delete mock DNS Record")?;
+        assert!(client.delete_dns_record(ZONE_ID, DNS_RECORD_ID).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn create_dns_record() -> anyhow::Result<()> {
+        let authentication = Authentication::Token(API_TOKEN.into());
+        let mut client = cloudflare::client::Client::new(&authentication);
+        client.post_dns_record = mock_dns_record_create;
+
+        let patch = DnsRecordPatch {
+            content: Some(IpAddr::V4(Ipv4Addr::new(198, 51, 100, 4))),
+            ttl: Some(1),
+            proxied: Some(false),
+            ..DnsRecordPatch::default()
+        };
+
+        assert_eq!(
+            client
+                .create_dns_record(ZONE_ID, "example.com", DnsRecordType::A, &patch)
+                .context("failed to create mock DNS Record")?,
+            DnsRecord::new(DNS_RECORD_ID, false, IpAddr::V4(Ipv4Addr::new(198, 51, 100, 4)), 1, false, None)
         );
 
         Ok(())
@@ -261,13 +578,20 @@ pub mod tests {
 
     #[test]
     fn update_dns_record() -> anyhow::Result<()> {
-        let mut client = cloudflare::client::Client::new(API_TOKEN);
+        let authentication = Authentication::Token(API_TOKEN.into());
+        let mut client = cloudflare::client::Client::new(&authentication);
         client.patch_dns_record = mock_dns_record_update;
 
+        let patch = DnsRecordPatch {
+            content: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            ttl: Some(300),
+            comment: Some("home".into()),
+            tags: Some(vec!["ddns:true".into()]),
+            ..DnsRecordPatch::default()
+        };
+
         assert_eq!(
-            client
-                .update_dns_record(ZONE_ID, DNS_RECORD_ID, IpAddr::V4(Ipv4Addr::LOCALHOST))
-                .context("failed to update mock DNS Record")?,
+            client.update_dns_record(ZONE_ID, DNS_RECORD_ID, &patch).context("failed to update mock DNS Record")?,
             ()
         );
 
@@ -275,15 +599,14 @@ pub mod tests {
     }
 
     #[test]
-    fn failure() -> anyhow::Result<()> {
-        let mut client = cloudflare::client::Client::new(API_TOKEN);
+    fn failure() {
+        let authentication = Authentication::Token(API_TOKEN.into());
+        let mut client = cloudflare::client::Client::new(&authentication);
         client.get_zone = mock_failure;
 
         assert_eq!(
             client.fetch_zone("example.com").unwrap_err().to_string(),
             include_str!("../../../resources/tests/cloudflare/failure.txt").trim()
         );
-
-        Ok(())
     }
 }
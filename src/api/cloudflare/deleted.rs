@@ -0,0 +1,15 @@
+use crate::api::cloudflare::api_result::ApiResult;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Deleted {
+    id: String,
+}
+
+impl Deleted {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl ApiResult for Deleted {}
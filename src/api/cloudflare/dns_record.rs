@@ -1,12 +1,22 @@
 use crate::api::cloudflare::api_result::ApiResult;
+use crate::api::cloudflare::dns_record_patch::DnsRecordPatch;
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
 
+/// The TTL value that tells Cloudflare to choose the TTL automatically.
+pub const TTL_AUTOMATIC: u32 = 1;
+
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct DnsRecord {
     id: String,
     locked: bool,
     content: IpAddr,
+    ttl: u32,
+    proxied: bool,
+    #[serde(default)]
+    comment: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
 }
 
 impl DnsRecord {
@@ -21,12 +31,59 @@ impl DnsRecord {
     pub const fn content(&self) -> IpAddr {
         self.content
     }
+
+    pub const fn ttl(&self) -> u32 {
+        self.ttl
+    }
+
+    pub const fn proxied(&self) -> bool {
+        self.proxied
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Updates the local copy of the record to match a patch that has been applied via the API.
+    pub fn apply(&mut self, patch: &DnsRecordPatch) {
+        if let Some(content) = patch.content {
+            self.content = content;
+        }
+
+        if let Some(ttl) = patch.ttl {
+            self.ttl = ttl;
+        }
+
+        if let Some(proxied) = patch.proxied {
+            self.proxied = proxied;
+        }
+
+        if let Some(comment) = &patch.comment {
+            self.comment = Some(comment.clone());
+        }
+
+        if let Some(tags) = &patch.tags {
+            self.tags.clone_from(tags);
+        }
+    }
 }
 
 #[cfg(test)]
 impl DnsRecord {
-    pub fn new(id: &str, locked: bool, content: IpAddr) -> Self {
-        Self { id: id.to_string(), locked, content }
+    pub fn new(id: &str, locked: bool, content: IpAddr, ttl: u32, proxied: bool, comment: Option<&str>) -> Self {
+        Self {
+            id: id.to_string(),
+            locked,
+            content,
+            ttl,
+            proxied,
+            comment: comment.map(str::to_string),
+            tags: Vec::new(),
+        }
     }
 }
 
@@ -0,0 +1,27 @@
+use serde::Serialize;
+use std::net::IpAddr;
+
+/// The fields of a DNS record to change; fields left as `None` are not sent, and so are left as they are.
+#[derive(Serialize, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct DnsRecordPatch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<IpAddr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxied: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+}
+
+impl DnsRecordPatch {
+    pub const fn is_empty(&self) -> bool {
+        self.content.is_none()
+            && self.ttl.is_none()
+            && self.proxied.is_none()
+            && self.comment.is_none()
+            && self.tags.is_none()
+    }
+}
@@ -1,12 +1,20 @@
+pub use authentication::Authentication;
 pub use client::Client;
+pub use dns_record::{DnsRecord, TTL_AUTOMATIC};
+pub use dns_record_patch::DnsRecordPatch;
 pub use dns_record_type::DnsRecordType;
+pub use zone::Zone;
 
 mod api_error;
 mod api_response;
 mod api_result;
+mod authentication;
 mod client;
+mod deleted;
 mod dns_record;
+mod dns_record_patch;
 mod dns_record_type;
+mod result_info;
 mod zone;
 
 #[cfg(test)]
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// Pagination details returned alongside collection results.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct ResultInfo {
+    page: u32,
+    total_pages: u32,
+}
+
+impl ResultInfo {
+    pub const fn is_last_page(self) -> bool {
+        self.page >= self.total_pages
+    }
+}
@@ -4,18 +4,23 @@ use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct Zone {
     id: String,
+    name: String,
 }
 
 impl Zone {
     pub fn id(&self) -> &str {
         &self.id
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 }
 
 #[cfg(test)]
 impl Zone {
-    pub fn new(id: &str) -> Self {
-        Self { id: id.to_string() }
+    pub fn new(id: &str, name: &str) -> Self {
+        Self { id: id.to_string(), name: name.to_string() }
     }
 }
 
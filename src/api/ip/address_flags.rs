@@ -0,0 +1,58 @@
+/// The kernel's flags for an interface address (`IFA_F_*` on Linux), as shown by `ip address show`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct AddressFlags(u32);
+
+impl AddressFlags {
+    /// An RFC 4941 privacy address, which is replaced every day or so.
+    pub const TEMPORARY: Self = Self(0x01);
+    /// Duplicate address detection failed, so the address cannot be used.
+    pub const DAD_FAILED: Self = Self(0x08);
+    /// The address' preferred lifetime has run out; it still works, but is on its way out.
+    pub const DEPRECATED: Self = Self(0x20);
+    /// Duplicate address detection has not finished yet, so the address cannot be used yet.
+    pub const TENTATIVE: Self = Self(0x40);
+    /// The stable SLAAC address that temporary addresses are generated from.
+    pub const MANAGE_TEMP_ADDRESS: Self = Self(0x100);
+    /// An RFC 7217 stable (but opaque) SLAAC address.
+    pub const STABLE_PRIVACY: Self = Self(0x800);
+
+    const NAMES: [(Self, &'static str); 6] = [
+        (Self::TEMPORARY, "temporary"),
+        (Self::DAD_FAILED, "dadfailed"),
+        (Self::DEPRECATED, "deprecated"),
+        (Self::TENTATIVE, "tentative"),
+        (Self::MANAGE_TEMP_ADDRESS, "mngtmpaddr"),
+        (Self::STABLE_PRIVACY, "stable-privacy"),
+    ];
+
+    pub const fn new(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    pub const fn contains(self, flags: Self) -> bool {
+        self.0 & flags.0 == flags.0
+    }
+
+    /// Whether the address can be used at all; i.e. it is neither still tentative, nor failed duplicate address
+    /// detection.
+    pub const fn is_usable(self) -> bool {
+        !self.contains(Self::TENTATIVE) && !self.contains(Self::DAD_FAILED)
+    }
+}
+
+impl std::ops::BitOr for AddressFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::fmt::Display for AddressFlags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<_> =
+            Self::NAMES.iter().filter(|(flag, _)| self.contains(*flag)).map(|(_, name)| *name).collect();
+
+        write!(f, "{}", names.join(" "))
+    }
+}
@@ -0,0 +1,743 @@
+use crate::api::ip::discovery::Discovery;
+use crate::api::ip::dns;
+use crate::api::ip::dns_query::DnsQuery;
+use crate::api::ip::endpoint::{Endpoint, DEFAULT_TIMEOUT};
+use crate::api::ip::fritzbox::Fritzbox;
+use crate::api::ip::interface_address::InterfaceAddress;
+use crate::api::ip::router::Router;
+use crate::api::ip::source::Source;
+use crate::api::ip::stun;
+use anyhow::Context;
+use std::fmt::Display;
+use std::net::{AddrParseError, IpAddr, Ipv4Addr, Ipv6Addr};
+use std::process::Command;
+use std::str::FromStr;
+use std::time::Duration;
+use ureq::{Request, Response};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Client<'a> {
+    v4: &'a Discovery,
+    v6: &'a Discovery,
+    fetch_v4: fn(Request) -> Result<Response, ureq::Error>,
+    fetch_v6: fn(Request) -> Result<Response, ureq::Error>,
+    list_interfaces: fn() -> anyhow::Result<Vec<InterfaceAddress>>,
+    run_command: fn(&str) -> anyhow::Result<String>,
+    query_stun: fn(&str, IpAddr, Duration) -> anyhow::Result<IpAddr>,
+    query_dns: fn(&DnsQuery, IpAddr, Duration) -> anyhow::Result<Vec<IpAddr>>,
+    query_router: fn(&Router, Duration) -> anyhow::Result<Ipv4Addr>,
+    query_fritzbox_v4: fn(&Fritzbox, Duration) -> anyhow::Result<Ipv4Addr>,
+    query_fritzbox_v6: fn(&Fritzbox, Duration) -> anyhow::Result<Ipv6Addr>,
+}
+
+impl<'a> Client<'a> {
+    pub fn new(v4: &'a Discovery, v6: &'a Discovery) -> Self {
+        Self {
+            v4,
+            v6,
+            fetch_v4: Self::get,
+            fetch_v6: Self::get,
+            list_interfaces: InterfaceAddress::list,
+            run_command: Self::run,
+            query_stun: stun::query,
+            query_dns: dns::query,
+            query_router: Router::query,
+            query_fritzbox_v4: Fritzbox::v4,
+            query_fritzbox_v6: Fritzbox::v6,
+        }
+    }
+
+    // mocked
+    #[cfg(not(tarpaulin_include))]
+    fn get(request: Request) -> Result<Response, ureq::Error> {
+        request.call()
+    }
+
+    /// Runs a command with the system shell, returning what it printed to stdout.
+    // mocked
+    #[cfg(not(tarpaulin_include))]
+    fn run(command: &str) -> anyhow::Result<String> {
+        let output = if cfg!(windows) {
+            Command::new("cmd").args(["/C", command]).output()
+        } else {
+            Command::new("sh").args(["-c", command]).output()
+        }
+        .with_context(|| format!("failed to run `{}`", command))?;
+
+        if !output.status.success() {
+            anyhow::bail!(
+                "`{}` failed ({}): {}",
+                command,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        String::from_utf8(output.stdout).with_context(|| format!("`{}` printed invalid UTF-8", command))
+    }
+
+    pub fn v4(self) -> anyhow::Result<Ipv4Addr> {
+        let family = |address| match address {
+            IpAddr::V4(address) => Some(address),
+            IpAddr::V6(_) => None,
+        };
+
+        match self.v4.source() {
+            Source::Http => Self::discover(self.v4, self.fetch_v4),
+            Source::Interface => self.read_interface(self.v4, family),
+            Source::Static => Self::read_static(self.v4, family),
+            Source::Command => self.read_command(self.v4, family),
+            Source::Stun => self.ask_stun(self.v4, Ipv4Addr::UNSPECIFIED.into(), family),
+            Source::Dns => self.ask_dns(self.v4, Ipv4Addr::UNSPECIFIED.into(), family),
+            Source::Router => self.ask_router(self.v4),
+            Source::Fritzbox => Self::ask_fritzbox(self.v4, self.query_fritzbox_v4),
+        }
+        .context("failed to discover IPv4 address")
+    }
+
+    pub fn v6(self) -> anyhow::Result<Ipv6Addr> {
+        let family = |address| match address {
+            IpAddr::V6(address) => Some(address),
+            IpAddr::V4(_) => None,
+        };
+
+        match self.v6.source() {
+            Source::Http => Self::discover(self.v6, self.fetch_v6),
+            Source::Interface => self.read_interface(self.v6, family),
+            Source::Static => Self::read_static(self.v6, family),
+            Source::Command => self.read_command(self.v6, family),
+            Source::Stun => self.ask_stun(self.v6, Ipv6Addr::UNSPECIFIED.into(), family),
+            Source::Dns => self.ask_dns(self.v6, Ipv6Addr::UNSPECIFIED.into(), family),
+            // cannot happen; rejected when the configuration is loaded
+            Source::Router => anyhow::bail!("routers only report their IPv4 address"),
+            Source::Fritzbox => Self::ask_fritzbox(self.v6, self.query_fritzbox_v6),
+        }
+        .context("failed to discover IPv6 address")
+    }
+
+    /// Whether any network interface has a global IPv4 address; without one, there is no IPv4 route to discover or
+    /// publish an address over.
+    pub fn has_global_v4(self) -> bool {
+        self.has_global(IpAddr::is_ipv4)
+    }
+
+    /// Whether any network interface has a global IPv6 address; as for [`Client::has_global_v4`], but a LAN that only
+    /// has unique local addresses (i.e. no IPv6 uplink) has no route either.
+    pub fn has_global_v6(self) -> bool {
+        self.has_global(IpAddr::is_ipv6)
+    }
+
+    /// Assumes there is an address when the interfaces cannot be listed, so that discovery is at least attempted.
+    fn has_global(self, family: fn(&IpAddr) -> bool) -> bool {
+        match (self.list_interfaces)() {
+            Ok(addresses) => addresses.iter().any(|address| family(&address.address()) && address.is_global()),
+            Err(e) => {
+                eprintln!("Warning: failed to list network interfaces; assuming a global address: {:#}", e);
+                true
+            }
+        }
+    }
+
+    fn read_interface<T>(self, discovery: &Discovery, family: fn(IpAddr) -> Option<T>) -> anyhow::Result<T> {
+        // cannot fail; an interface is required when the configuration is loaded
+        let interface = discovery.interface().context("no network interface configured")?;
+
+        interface.select(&(self.list_interfaces)()?, family)
+    }
+
+    fn read_static<T>(discovery: &Discovery, family: fn(IpAddr) -> Option<T>) -> anyhow::Result<T> {
+        // cannot fail; an address is required when the configuration is loaded
+        let address = discovery.address().context("no static address configured")?;
+
+        family(address).with_context(|| format!("{} is not in the right address family", address))
+    }
+
+    fn read_command<T>(self, discovery: &Discovery, family: fn(IpAddr) -> Option<T>) -> anyhow::Result<T> {
+        // cannot fail; a command is required when the configuration is loaded
+        let command = discovery.command().context("no command configured")?;
+
+        let output = (self.run_command)(command)?;
+        let address = IpAddr::from_str(output.trim())
+            .with_context(|| format!("`{}` printed an invalid address: {}", command, output.trim()))?;
+
+        family(address).with_context(|| format!("`{}` printed an address in the wrong family: {}", command, address))
+    }
+
+    /// Asks each STUN server in turn, from a socket bound to `local`, until as many of them as the quorum agree on an
+    /// address.
+    fn ask_stun<T>(self, discovery: &Discovery, local: IpAddr, family: fn(IpAddr) -> Option<T>) -> anyhow::Result<T>
+    where
+        T: Display + Eq + Copy,
+    {
+        let timeout = discovery.timeout().unwrap_or(DEFAULT_TIMEOUT);
+
+        Self::vote(
+            discovery.quorum(),
+            discovery.stun().iter().map(|server| {
+                let ip = (self.query_stun)(server, local, timeout)?;
+
+                family(ip).with_context(|| format!("STUN server {} responded with the wrong family: {}", server, ip))
+            }),
+        )
+    }
+
+    /// Asks each DNS resolver its question in turn, from a socket bound to `local`, until as many of them as the
+    /// quorum agree on an address.
+    fn ask_dns<T>(self, discovery: &Discovery, local: IpAddr, family: fn(IpAddr) -> Option<T>) -> anyhow::Result<T>
+    where
+        T: Display + Eq + Copy,
+    {
+        let timeout = discovery.timeout().unwrap_or(DEFAULT_TIMEOUT);
+
+        Self::vote(
+            discovery.quorum(),
+            discovery.dns().iter().map(|question| {
+                let addresses = (self.query_dns)(question, local, timeout)?;
+
+                addresses.into_iter().find_map(family).with_context(|| {
+                    format!(
+                        "{} at {} answered with no address in the right family",
+                        question.name(),
+                        question.resolver()
+                    )
+                })
+            }),
+        )
+    }
+
+    fn ask_router(self, discovery: &Discovery) -> anyhow::Result<Ipv4Addr> {
+        let router = discovery.router().cloned().unwrap_or_default();
+
+        (self.query_router)(&router, discovery.timeout().unwrap_or(DEFAULT_TIMEOUT))
+    }
+
+    fn ask_fritzbox<T>(
+        discovery: &Discovery,
+        query: fn(&Fritzbox, Duration) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        // cannot fail; a FRITZ!Box is filled in when the configuration is loaded
+        let fritzbox = discovery.fritzbox().context("no FRITZ!Box configured")?;
+
+        query(fritzbox, discovery.timeout().unwrap_or(DEFAULT_TIMEOUT))
+    }
+
+    /// Asks each provider in turn, until as many of them as the quorum agree on an address.
+    fn discover<T>(discovery: &Discovery, fetch: fn(Request) -> Result<Response, ureq::Error>) -> anyhow::Result<T>
+    where
+        T: FromStr<Err = AddrParseError> + Display + Eq + Copy,
+    {
+        Self::vote(
+            discovery.quorum(),
+            discovery.providers().iter().map(|endpoint| {
+                Self::fetch(endpoint, fetch).and_then(|ip| {
+                    T::from_str(&ip)
+                        .with_context(|| format!("{} responded with an invalid address: {}", endpoint.url(), ip))
+                })
+            }),
+        )
+    }
+
+    /// Takes answers until `quorum` of them agree; answers are only produced (i.e. providers asked) as needed.
+    fn vote<T>(quorum: usize, answers: impl Iterator<Item = anyhow::Result<T>>) -> anyhow::Result<T>
+    where
+        T: Display + Eq + Copy,
+    {
+        let mut votes: Vec<(T, usize)> = Vec::new();
+
+        for ip in answers {
+            let ip = match ip {
+                Ok(ip) => ip,
+                Err(e) => {
+                    eprintln!("IP discovery provider failed: {:#}", e);
+                    continue;
+                }
+            };
+
+            match votes.iter_mut().find(|(vote, _)| *vote == ip) {
+                Some((_, count)) => *count += 1,
+                None => votes.push((ip, 1)),
+            }
+
+            if let Some(&(ip, _)) = votes.iter().find(|(_, count)| *count >= quorum) {
+                return Ok(ip);
+            }
+        }
+
+        if votes.is_empty() {
+            anyhow::bail!("every provider failed; see stderr for details");
+        }
+
+        let votes: Vec<_> = votes.iter().map(|(ip, count)| format!("{} ({})", ip, count)).collect();
+        anyhow::bail!("fewer than {} providers agreed on an address: {}", quorum, votes.join(", "))
+    }
+
+    fn fetch(endpoint: &Endpoint, fetch: fn(Request) -> Result<Response, ureq::Error>) -> anyhow::Result<String> {
+        let request = ureq::get(endpoint.url()).timeout(endpoint.timeout().unwrap_or(DEFAULT_TIMEOUT));
+
+        match fetch(request) {
+            Ok(response) => {
+                let body = response.into_string().context("failed to read response")?;
+
+                endpoint.extract().apply(&body).with_context(|| format!("unexpected response from {}", endpoint.url()))
+            }
+            Err(ureq::Error::Status(code, _)) => anyhow::bail!("{} responded with: {}", endpoint.url(), code),
+            Err(ureq::Error::Transport(e)) => {
+                anyhow::bail!("transport error encountered when fetching from {}: {}", endpoint.url(), e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl Client<'_> {
+    pub fn set_fetch_v4(&mut self, fetch: fn(Request) -> Result<Response, ureq::Error>) {
+        self.fetch_v4 = fetch;
+    }
+
+    pub fn set_fetch_v6(&mut self, fetch: fn(Request) -> Result<Response, ureq::Error>) {
+        self.fetch_v6 = fetch;
+    }
+
+    pub fn set_list_interfaces(&mut self, list: fn() -> anyhow::Result<Vec<InterfaceAddress>>) {
+        self.list_interfaces = list;
+    }
+
+    pub fn set_run_command(&mut self, run: fn(&str) -> anyhow::Result<String>) {
+        self.run_command = run;
+    }
+
+    pub fn set_query_stun(&mut self, query: fn(&str, IpAddr, Duration) -> anyhow::Result<IpAddr>) {
+        self.query_stun = query;
+    }
+
+    pub fn set_query_dns(&mut self, query: fn(&DnsQuery, IpAddr, Duration) -> anyhow::Result<Vec<IpAddr>>) {
+        self.query_dns = query;
+    }
+
+    pub fn set_query_router(&mut self, query: fn(&Router, Duration) -> anyhow::Result<Ipv4Addr>) {
+        self.query_router = query;
+    }
+
+    pub fn set_query_fritzbox_v4(&mut self, query: fn(&Fritzbox, Duration) -> anyhow::Result<Ipv4Addr>) {
+        self.query_fritzbox_v4 = query;
+    }
+
+    pub fn set_query_fritzbox_v6(&mut self, query: fn(&Fritzbox, Duration) -> anyhow::Result<Ipv6Addr>) {
+        self.query_fritzbox_v6 = query;
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::api;
+    use crate::api::ip::address_flags::AddressFlags;
+    use crate::api::ip::interface_address::InterfaceAddress;
+    use crate::api::ip::{
+        Discovery, DnsQuery, Endpoint, Extract, Fritzbox, Interface, QueryClass, Router, RouterProtocol, Source,
+    };
+    use anyhow::Context;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::time::Duration;
+    use ureq::{Request, Response};
+
+    pub fn mock_v4(_: Request) -> Result<Response, ureq::Error> {
+        Response::new(200, "OK", "203.0.113.4")
+    }
+
+    pub fn mock_v6(_: Request) -> Result<Response, ureq::Error> {
+        Response::new(200, "OK", "2001:db8::1")
+    }
+
+    pub fn mock_trace(_: Request) -> Result<Response, ureq::Error> {
+        Response::new(200, "OK", include_str!("../../../resources/tests/ip/trace.txt"))
+    }
+
+    #[allow(clippy::unnecessary_wraps)] // must match the signature of the real listing
+    pub fn mock_interfaces() -> anyhow::Result<Vec<InterfaceAddress>> {
+        Ok(vec![
+            InterfaceAddress::new("lo", IpAddr::V4(Ipv4Addr::LOCALHOST), AddressFlags::default()),
+            InterfaceAddress::new("eth0", IpAddr::V4(Ipv4Addr::new(198, 51, 100, 4)), AddressFlags::default()),
+            InterfaceAddress::new(
+                "eth0",
+                IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1)),
+                AddressFlags::default(),
+            ),
+            InterfaceAddress::new(
+                "eth0",
+                IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 5)),
+                AddressFlags::TEMPORARY,
+            ),
+            InterfaceAddress::new(
+                "eth0",
+                IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 4)),
+                AddressFlags::MANAGE_TEMP_ADDRESS,
+            ),
+        ])
+    }
+
+    /// A host on a LAN without an IPv6 uplink, whose only usable IPv6 address is unique local; the global ones are
+    /// deprecated, or were never confirmed unique.
+    #[allow(clippy::unnecessary_wraps)] // must match the signature of the real listing
+    pub fn mock_interfaces_ula_only() -> anyhow::Result<Vec<InterfaceAddress>> {
+        Ok(vec![
+            InterfaceAddress::new("eth0", IpAddr::V4(Ipv4Addr::new(192, 168, 1, 4)), AddressFlags::default()),
+            InterfaceAddress::new(
+                "eth0",
+                IpAddr::V6(Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 4)),
+                AddressFlags::default(),
+            ),
+            InterfaceAddress::new(
+                "eth0",
+                IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 4)),
+                AddressFlags::DEPRECATED,
+            ),
+            InterfaceAddress::new(
+                "eth0",
+                IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 5)),
+                AddressFlags::TENTATIVE | AddressFlags::DAD_FAILED,
+            ),
+        ])
+    }
+
+    /// A host whose only IPv6 address is link-local, i.e. one without IPv6 connectivity.
+    #[allow(clippy::unnecessary_wraps)] // must match the signature of the real listing
+    pub fn mock_interfaces_v4_only() -> anyhow::Result<Vec<InterfaceAddress>> {
+        Ok(vec![
+            InterfaceAddress::new("lo", IpAddr::V6(Ipv6Addr::LOCALHOST), AddressFlags::default()),
+            InterfaceAddress::new("eth0", IpAddr::V4(Ipv4Addr::new(198, 51, 100, 4)), AddressFlags::default()),
+            InterfaceAddress::new(
+                "eth0",
+                IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1)),
+                AddressFlags::default(),
+            ),
+        ])
+    }
+
+    /// Providers whose URL mentions "failing" fail, and those that mention "other" respond with a different address.
+    #[allow(clippy::needless_pass_by_value)] // must match the signature of the real request
+    fn mock_providers(request: Request) -> Result<Response, ureq::Error> {
+        if request.url().contains("failing") {
+            Response::new(503, "Service Unavailable", "")
+        } else if request.url().contains("other") {
+            Response::new(200, "OK", "198.51.100.7")
+        } else {
+            Response::new(200, "OK", "198.51.100.4")
+        }
+    }
+
+    /// Prints the command's first word, so the tests can choose the output.
+    #[allow(clippy::unnecessary_wraps)] // must match the signature of the real command
+    pub fn mock_command(command: &str) -> anyhow::Result<String> {
+        Ok(format!("{}\n", command.split_whitespace().next().unwrap_or_default()))
+    }
+
+    /// Servers whose name mentions "failing" fail, and those that mention "other" respond with a different address.
+    pub fn mock_stun(server: &str, local: IpAddr, _: Duration) -> anyhow::Result<IpAddr> {
+        if server.contains("failing") {
+            anyhow::bail!("STUN server {} did not respond", server);
+        }
+
+        Ok(match (local, server.contains("other")) {
+            (IpAddr::V4(_), false) => IpAddr::V4(Ipv4Addr::new(203, 0, 113, 4)),
+            (IpAddr::V4(_), true) => IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)),
+            (IpAddr::V6(_), false) => IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+            (IpAddr::V6(_), true) => IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 7)),
+        })
+    }
+
+    /// Questions for resolvers whose address mentions "failing" fail; the others answer with a CNAME's target (which
+    /// is ignored) and an address in each family.
+    pub fn mock_dns(question: &DnsQuery, _: IpAddr, _: Duration) -> anyhow::Result<Vec<IpAddr>> {
+        if question.resolver().contains("failing") {
+            anyhow::bail!("DNS resolver {} did not respond", question.resolver());
+        }
+
+        Ok(vec![IpAddr::V4(Ipv4Addr::new(203, 0, 113, 4)), IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1))])
+    }
+
+    /// Routers configured with a gateway that mentions "failing" fail.
+    pub fn mock_router(router: &Router, _: Duration) -> anyhow::Result<Ipv4Addr> {
+        if router.gateway().is_some_and(|gateway| gateway.contains("failing")) {
+            anyhow::bail!("the router did not report its WAN address");
+        }
+
+        Ok(Ipv4Addr::new(203, 0, 113, 4))
+    }
+
+    /// FRITZ!Boxes whose URL mentions "failing" fail.
+    pub fn mock_fritzbox_v4(fritzbox: &Fritzbox, _: Duration) -> anyhow::Result<Ipv4Addr> {
+        if fritzbox.url().contains("failing") {
+            anyhow::bail!("the FRITZ!Box did not report its IPv4 address");
+        }
+
+        Ok(Ipv4Addr::new(203, 0, 113, 4))
+    }
+
+    /// As for [`mock_fritzbox_v4`]; the prefix is reported as its first address.
+    pub fn mock_fritzbox_v6(fritzbox: &Fritzbox, _: Duration) -> anyhow::Result<Ipv6Addr> {
+        if fritzbox.url().contains("failing") {
+            anyhow::bail!("the FRITZ!Box did not report its IPv6 address");
+        }
+
+        Ok(if fritzbox.prefix() {
+            Ipv6Addr::new(0x2001, 0xdb8, 0xaa00, 0, 0, 0, 0, 0)
+        } else {
+            Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)
+        })
+    }
+
+    fn discovery(urls: &[&str], quorum: usize) -> Discovery {
+        Discovery::new(urls.iter().map(|url| Endpoint::new(url, Extract::Plain)).collect(), quorum)
+    }
+
+    #[test]
+    fn v4() -> anyhow::Result<()> {
+        let (v4, v6) = (Discovery::default_v4(), Discovery::default_v6());
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.fetch_v4 = mock_v4;
+
+        assert_eq!(client.v4().context("failed to fetch mock IPv4 address")?, Ipv4Addr::new(203, 0, 113, 4));
+
+        Ok(())
+    }
+
+    #[test]
+    fn v6() -> anyhow::Result<()> {
+        let (v4, v6) = (Discovery::default_v4(), Discovery::default_v6());
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.fetch_v6 = mock_v6;
+
+        assert_eq!(
+            client.v6().context("failed to fetch mock IPv6 address")?,
+            Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn extract() -> anyhow::Result<()> {
+        let v4 = Discovery::new(vec![Endpoint::new("https://1.1.1.1/cdn-cgi/trace", Extract::Key("ip".into()))], 1);
+        let v6 = Discovery::new(vec![Endpoint::new("https://api6.ipify.org/", Extract::Plain)], 1);
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.fetch_v4 = mock_trace;
+        client.fetch_v6 = mock_trace;
+
+        assert_eq!(client.v4().context("failed to fetch mock IPv4 address")?, Ipv4Addr::new(198, 51, 100, 4));
+        assert!(client.v6().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn fallback() -> anyhow::Result<()> {
+        let v6 = Discovery::default_v6();
+        let expected = Ipv4Addr::new(198, 51, 100, 4);
+
+        let v4 = discovery(&["https://failing.example/", "https://first.example/"], 1);
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.fetch_v4 = mock_providers;
+        assert_eq!(client.v4().context("failed to fall back")?, expected);
+
+        let v4 = discovery(&["https://failing.example/"], 1);
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.fetch_v4 = mock_providers;
+        assert!(client.v4().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn quorum() -> anyhow::Result<()> {
+        let v6 = Discovery::default_v6();
+        let expected = Ipv4Addr::new(198, 51, 100, 4);
+
+        let v4 = discovery(&["https://first.example/", "https://other.example/", "https://second.example/"], 2);
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.fetch_v4 = mock_providers;
+        assert_eq!(client.v4().context("failed to reach quorum")?, expected);
+
+        let v4 = discovery(&["https://first.example/", "https://other.example/", "https://failing.example/"], 2);
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.fetch_v4 = mock_providers;
+        assert!(client.v4().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn interface() -> anyhow::Result<()> {
+        let v4 = Discovery::default_v4();
+        let mut v6 = Discovery::default_v6();
+        v6.set_source(Source::Interface);
+        v6.set_interface(Interface::new("eth0"));
+
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.fetch_v4 = mock_v4;
+        client.list_interfaces = mock_interfaces;
+
+        assert_eq!(client.v4().context("failed to fetch mock IPv4 address")?, Ipv4Addr::new(203, 0, 113, 4));
+        assert_eq!(
+            client.v6().context("failed to read mock IPv6 address")?,
+            Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 4)
+        );
+        assert!(client.has_global_v4());
+        assert!(client.has_global_v6());
+
+        client.list_interfaces = mock_interfaces_v4_only;
+        assert!(client.has_global_v4());
+        assert!(!client.has_global_v6());
+
+        // a private IPv4 address is usually behind NAT, but a unique local IPv6 address is not translated at all
+        client.list_interfaces = mock_interfaces_ula_only;
+        assert!(client.has_global_v4());
+        assert!(!client.has_global_v6());
+
+        Ok(())
+    }
+
+    #[test]
+    fn static_and_command() -> anyhow::Result<()> {
+        let mut v4 = Discovery::default_v4();
+        v4.set_source(Source::Static);
+        v4.set_address(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 4)));
+
+        let mut v6 = Discovery::default_v6();
+        v6.set_source(Source::Command);
+        v6.set_command("2001:db8::4 # e.g. an SNMP query");
+
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.fetch_v4 = mock_providers;
+        client.fetch_v6 = mock_providers;
+        client.run_command = mock_command;
+
+        assert_eq!(client.v4().context("failed to read static IPv4 address")?, Ipv4Addr::new(203, 0, 113, 4));
+        assert_eq!(
+            client.v6().context("failed to read IPv6 address from command")?,
+            Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 4)
+        );
+
+        v6.set_command("203.0.113.4");
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.run_command = mock_command;
+        assert!(client.v6().is_err());
+
+        v6.set_command("unknown");
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.run_command = mock_command;
+        assert!(client.v6().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn stun() -> anyhow::Result<()> {
+        let mut v4 = Discovery::default_v4();
+        v4.set_source(Source::Stun);
+        v4.set_stun(vec!["failing.example".into(), "first.example".into()]);
+
+        let mut v6 = Discovery::default_v6();
+        v6.set_source(Source::Stun);
+        v6.set_stun(vec!["first.example".into(), "other.example".into(), "second.example:3478".into()]);
+        v6.set_quorum(2);
+
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.query_stun = mock_stun;
+
+        assert_eq!(client.v4().context("failed to ask mock STUN servers")?, Ipv4Addr::new(203, 0, 113, 4));
+        assert_eq!(
+            client.v6().context("failed to ask mock STUN servers")?,
+            Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)
+        );
+
+        v4.set_stun(vec!["failing.example".into()]);
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.query_stun = mock_stun;
+        assert!(client.v4().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn dns() -> anyhow::Result<()> {
+        let mut v4 = Discovery::default_v4();
+        v4.set_source(Source::Dns);
+        v4.set_dns(vec![
+            DnsQuery::new("failing.example", "myip.example", None, QueryClass::In),
+            DnsQuery::new("resolver.example", "myip.example", None, QueryClass::In),
+        ]);
+
+        let mut v6 = Discovery::default_v6();
+        v6.set_source(Source::Dns);
+        v6.set_dns(Discovery::default_dns_v6());
+
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.query_dns = mock_dns;
+
+        assert_eq!(client.v4().context("failed to ask mock DNS resolvers")?, Ipv4Addr::new(203, 0, 113, 4));
+        assert_eq!(
+            client.v6().context("failed to ask mock DNS resolvers")?,
+            Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)
+        );
+
+        v4.set_dns(vec![DnsQuery::new("failing.example", "myip.example", None, QueryClass::In)]);
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.query_dns = mock_dns;
+        assert!(client.v4().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn router() -> anyhow::Result<()> {
+        let mut v4 = Discovery::default_v4();
+        v4.set_source(Source::Router);
+
+        let mut v6 = Discovery::default_v6();
+        v6.set_source(Source::Router);
+
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.query_router = mock_router;
+
+        assert_eq!(client.v4().context("failed to ask mock router")?, Ipv4Addr::new(203, 0, 113, 4));
+        assert!(client.v6().is_err());
+
+        v4.set_router(Router::new(RouterProtocol::NatPmp, Some("failing.example".into())));
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.query_router = mock_router;
+        assert!(client.v4().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn fritzbox() -> anyhow::Result<()> {
+        let mut v4 = Discovery::default_v4();
+        v4.set_source(Source::Fritzbox);
+        v4.set_fritzbox(Fritzbox::default());
+
+        let mut prefix = Fritzbox::default();
+        prefix.set_prefix(true);
+
+        let mut v6 = Discovery::default_v6();
+        v6.set_source(Source::Fritzbox);
+        v6.set_fritzbox(prefix);
+
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.query_fritzbox_v4 = mock_fritzbox_v4;
+        client.query_fritzbox_v6 = mock_fritzbox_v6;
+
+        assert_eq!(client.v4().context("failed to ask mock FRITZ!Box")?, Ipv4Addr::new(203, 0, 113, 4));
+        assert_eq!(
+            client.v6().context("failed to ask mock FRITZ!Box")?,
+            Ipv6Addr::new(0x2001, 0xdb8, 0xaa00, 0, 0, 0, 0, 0)
+        );
+
+        v4.set_fritzbox(Fritzbox::new("http://failing.example:49000"));
+        let mut client = api::ip::Client::new(&v4, &v6);
+        client.query_fritzbox_v4 = mock_fritzbox_v4;
+        assert!(client.v4().is_err());
+
+        Ok(())
+    }
+}
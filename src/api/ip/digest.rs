@@ -0,0 +1,123 @@
+//! Just enough HTTP Digest authentication (RFC 7616) to log in to a FRITZ!Box: MD5, with or without `qop=auth`.
+
+use crate::api::ip::md5;
+use anyhow::Context;
+use std::fmt::Write;
+
+/// A `WWW-Authenticate: Digest ...` challenge.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Challenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    /// Whether the server asked for `qop=auth`; servers that predate RFC 2617 leave it out.
+    qop: bool,
+}
+
+impl Challenge {
+    pub fn parse(header: &str) -> anyhow::Result<Self> {
+        let header = header.trim();
+        let (scheme, rest) = header.split_once(' ').unwrap_or((header, ""));
+
+        if !scheme.eq_ignore_ascii_case("digest") {
+            anyhow::bail!("expected a Digest challenge; got: {}", header);
+        }
+
+        let params = params(rest);
+        let param =
+            |name: &str| params.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.clone());
+
+        if let Some(algorithm) = param("algorithm").filter(|algorithm| !algorithm.eq_ignore_ascii_case("md5")) {
+            anyhow::bail!("unsupported Digest algorithm: {}", algorithm);
+        }
+
+        let qop = match param("qop") {
+            None => false,
+            Some(qop) if qop.split(',').any(|qop| qop.trim() == "auth") => true,
+            Some(qop) => anyhow::bail!("unsupported Digest quality of protection: {}", qop),
+        };
+
+        Ok(Self {
+            realm: param("realm").context("Digest challenge has no realm")?,
+            nonce: param("nonce").context("Digest challenge has no nonce")?,
+            opaque: param("opaque"),
+            qop,
+        })
+    }
+
+    /// The `Authorization` header that answers this challenge, for the first request made with its nonce.
+    pub fn authorization(&self, username: &str, password: &str, method: &str, uri: &str, cnonce: &str) -> String {
+        let credentials = md5::hex(format!("{}:{}:{}", username, self.realm, password).as_bytes());
+        let request = md5::hex(format!("{}:{}", method, uri).as_bytes());
+
+        let response = if self.qop {
+            md5::hex(format!("{}:{}:00000001:{}:auth:{}", credentials, self.nonce, cnonce, request).as_bytes())
+        } else {
+            md5::hex(format!("{}:{}:{}", credentials, self.nonce, request).as_bytes())
+        };
+
+        let mut header = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm=MD5, response=\"{}\"",
+            username, self.realm, self.nonce, uri, response
+        );
+
+        if self.qop {
+            let _ = write!(header, ", qop=auth, nc=00000001, cnonce=\"{}\"", cnonce);
+        }
+
+        if let Some(opaque) = &self.opaque {
+            let _ = write!(header, ", opaque=\"{}\"", opaque);
+        }
+
+        header
+    }
+}
+
+/// Splits a list of `name=value` parameters, whose values may be quoted (and then contain commas).
+pub fn params(list: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut rest = list.trim();
+
+    while let Some((name, value)) = rest.split_once('=') {
+        let value = value.trim_start();
+
+        let (value, after) = value.strip_prefix('"').map_or_else(
+            || value.split_once(',').unwrap_or((value, "")),
+            |quoted| quoted.split_once('"').unwrap_or((quoted, "")),
+        );
+
+        params.push((name.trim().to_string(), value.trim().to_string()));
+        rest = after.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+    }
+
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::ip::digest::{self, Challenge};
+
+    #[test]
+    fn authorization() -> anyhow::Result<()> {
+        // the example from RFC 2617
+        let challenge = Challenge::parse(
+            "Digest realm=\"testrealm@host.com\", qop=\"auth,auth-int\", \
+             nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"",
+        )?;
+        let authorization = challenge.authorization("Mufasa", "Circle Of Life", "GET", "/dir/index.html", "0a4f113b");
+
+        let params = digest::params(authorization.trim_start_matches("Digest "));
+        assert!(params.contains(&("response".into(), "6629fae49393a05397450978507c4ef1".into())));
+        assert!(params.contains(&("opaque".into(), "5ccc069c403ebaf9f0171e9517f40e41".into())));
+        assert!(params.contains(&("nc".into(), "00000001".into())));
+
+        let legacy = Challenge::parse("Digest realm=\"F!Box SOAP-Auth\", nonce=\"4EA9FC4F9C1B1D3B\"")?;
+        assert!(!legacy.authorization("ddns", "secret", "POST", "/ctl", "1").contains("qop"));
+
+        assert!(Challenge::parse("Basic realm=\"FRITZ!Box\"").is_err());
+        assert!(Challenge::parse("Digest realm=\"x\", nonce=\"y\", algorithm=SHA-256").is_err());
+        assert!(Challenge::parse("Digest realm=\"x\"").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+use crate::api::ip::dns_query::DnsQuery;
+use crate::api::ip::endpoint::Endpoint;
+use crate::api::ip::extract::Extract;
+use crate::api::ip::fritzbox::Fritzbox;
+use crate::api::ip::interface::Interface;
+use crate::api::ip::query_class::QueryClass;
+use crate::api::ip::query_type::QueryType;
+use crate::api::ip::router::Router;
+use crate::api::ip::source::Source;
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// How an address family is discovered; from HTTP providers, STUN servers or DNS resolvers (tried in order), a local
+/// network interface, a static address, the output of a command, the local router, or a FRITZ!Box.
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct Discovery {
+    #[serde(default)]
+    source: Source,
+    #[serde(default)]
+    providers: Vec<Endpoint>,
+    /// How many providers (or STUN servers, or DNS queries) must agree on an address before it is trusted; 1 just falls
+    /// back to the next one whenever one fails.
+    #[serde(default = "Discovery::default_quorum")]
+    quorum: usize,
+    interface: Option<Interface>,
+    address: Option<IpAddr>,
+    /// Run with the system shell (`sh -c` or `cmd /C`); its output should be just the address.
+    command: Option<String>,
+    /// `host`, `host:port` or an IP address; the port defaults to 3478.
+    #[serde(default)]
+    stun: Vec<String>,
+    #[serde(default)]
+    dns: Vec<DnsQuery>,
+    router: Option<Router>,
+    fritzbox: Option<Fritzbox>,
+    /// How long to wait for each STUN server, DNS resolver, router protocol or FRITZ!Box call, set from `ip_timeout`.
+    #[serde(skip)]
+    timeout: Option<Duration>,
+}
+
+impl Discovery {
+    pub const fn new(providers: Vec<Endpoint>, quorum: usize) -> Self {
+        Self {
+            source: Source::Http,
+            providers,
+            quorum,
+            interface: None,
+            address: None,
+            command: None,
+            stun: Vec::new(),
+            dns: Vec::new(),
+            router: None,
+            fritzbox: None,
+            timeout: None,
+        }
+    }
+
+    pub fn default_v4() -> Self {
+        Self::new(
+            vec![
+                Endpoint::new("https://api.ipify.org/", Extract::Plain),
+                Endpoint::new("https://1.1.1.1/cdn-cgi/trace", Extract::Key("ip".into())),
+                Endpoint::new("https://ipv4.icanhazip.com/", Extract::Plain),
+            ],
+            Self::default_quorum(),
+        )
+    }
+
+    pub fn default_v6() -> Self {
+        Self::new(
+            vec![
+                Endpoint::new("https://api6.ipify.org/", Extract::Plain),
+                Endpoint::new("https://[2606:4700:4700::1111]/cdn-cgi/trace", Extract::Key("ip".into())),
+                Endpoint::new("https://ipv6.icanhazip.com/", Extract::Plain),
+            ],
+            Self::default_quorum(),
+        )
+    }
+
+    pub fn default_stun() -> Vec<String> {
+        vec!["stun.cloudflare.com:3478".into(), "stun.l.google.com:19302".into()]
+    }
+
+    pub fn default_dns_v4() -> Vec<DnsQuery> {
+        vec![
+            DnsQuery::new("1.1.1.1", "whoami.cloudflare", Some(QueryType::Txt), QueryClass::Ch),
+            DnsQuery::new("208.67.222.222", "myip.opendns.com", None, QueryClass::In),
+        ]
+    }
+
+    pub fn default_dns_v6() -> Vec<DnsQuery> {
+        vec![
+            DnsQuery::new("2606:4700:4700::1111", "whoami.cloudflare", Some(QueryType::Txt), QueryClass::Ch),
+            DnsQuery::new("2620:119:35::35", "myip.opendns.com", None, QueryClass::In),
+        ]
+    }
+
+    const fn default_quorum() -> usize {
+        1
+    }
+
+    pub const fn source(&self) -> Source {
+        self.source
+    }
+
+    pub const fn set_source(&mut self, source: Source) {
+        self.source = source;
+    }
+
+    pub fn providers(&self) -> &[Endpoint] {
+        &self.providers
+    }
+
+    pub fn providers_mut(&mut self) -> &mut [Endpoint] {
+        &mut self.providers
+    }
+
+    pub fn set_providers(&mut self, providers: Vec<Endpoint>) {
+        self.providers = providers;
+    }
+
+    pub const fn quorum(&self) -> usize {
+        self.quorum
+    }
+
+    pub const fn set_quorum(&mut self, quorum: usize) {
+        self.quorum = quorum;
+    }
+
+    pub const fn interface(&self) -> Option<&Interface> {
+        self.interface.as_ref()
+    }
+
+    pub const fn interface_mut(&mut self) -> Option<&mut Interface> {
+        self.interface.as_mut()
+    }
+
+    pub fn set_interface(&mut self, interface: Interface) {
+        self.interface = Some(interface);
+    }
+
+    pub const fn address(&self) -> Option<IpAddr> {
+        self.address
+    }
+
+    pub const fn set_address(&mut self, address: IpAddr) {
+        self.address = Some(address);
+    }
+
+    pub fn command(&self) -> Option<&str> {
+        self.command.as_deref()
+    }
+
+    pub fn set_command(&mut self, command: &str) {
+        self.command = Some(command.to_string());
+    }
+
+    pub fn stun(&self) -> &[String] {
+        &self.stun
+    }
+
+    pub fn set_stun(&mut self, servers: Vec<String>) {
+        self.stun = servers;
+    }
+
+    pub fn dns(&self) -> &[DnsQuery] {
+        &self.dns
+    }
+
+    pub fn set_dns(&mut self, queries: Vec<DnsQuery>) {
+        self.dns = queries;
+    }
+
+    pub const fn router(&self) -> Option<&Router> {
+        self.router.as_ref()
+    }
+
+    pub fn set_router(&mut self, router: Router) {
+        self.router = Some(router);
+    }
+
+    pub const fn fritzbox(&self) -> Option<&Fritzbox> {
+        self.fritzbox.as_ref()
+    }
+
+    pub const fn fritzbox_mut(&mut self) -> Option<&mut Fritzbox> {
+        self.fritzbox.as_mut()
+    }
+
+    pub fn set_fritzbox(&mut self, fritzbox: Fritzbox) {
+        self.fritzbox = Some(fritzbox);
+    }
+
+    pub const fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub const fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = Some(timeout);
+    }
+}
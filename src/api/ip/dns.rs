@@ -0,0 +1,289 @@
+//! Just enough of the DNS wire format (RFC 1035) to ask a resolver a "whoami" question over UDP, and read the
+//! addresses in its answer.
+
+use crate::api::ip::dns_query::DnsQuery;
+use crate::api::ip::query_type::QueryType;
+use crate::api::ip::udp;
+use anyhow::Context;
+use std::convert::{TryFrom, TryInto};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+/// The port DNS resolvers listen on when the resolver address does not give one.
+pub const DEFAULT_PORT: u16 = 53;
+
+const HEADER_LEN: usize = 12;
+
+/// Flags: a query, asking for recursion.
+const FLAGS_QUERY: u16 = 0x0100;
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_TRUNCATED: u16 = 0x0200;
+const RCODE_MASK: u16 = 0x000f;
+
+const TYPE_A: u16 = 1;
+const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+
+/// Asks a resolver a question, from a socket bound to `local` (the unspecified address of the family to discover),
+/// returning every address in its answer; the query is retransmitted with exponential backoff until `timeout` runs
+/// out.
+// mocked
+#[cfg(not(tarpaulin_include))]
+pub fn query(question: &DnsQuery, local: IpAddr, timeout: Duration) -> anyhow::Result<Vec<IpAddr>> {
+    query_with(question, local, timeout, fastrand::u16(..))
+}
+
+fn query_with(question: &DnsQuery, local: IpAddr, timeout: Duration, id: u16) -> anyhow::Result<Vec<IpAddr>> {
+    let query_type =
+        question.query_type().unwrap_or_else(|| if local.is_ipv4() { QueryType::A } else { QueryType::Aaaa });
+    let request = request(id, question.name(), query_type.code(), question.class().code())?;
+
+    udp::exchange(
+        "DNS resolver",
+        question.resolver(),
+        DEFAULT_PORT,
+        local,
+        timeout,
+        |_| request,
+        |response| parse(response, id),
+    )
+    .with_context(|| format!("failed to look up {} at {}", question.name(), question.resolver()))
+}
+
+/// Builds a query with a single question.
+pub fn request(id: u16, name: &str, query_type: u16, class: u16) -> anyhow::Result<Vec<u8>> {
+    let mut request = Vec::with_capacity(HEADER_LEN + name.len() + 6);
+
+    request.extend_from_slice(&id.to_be_bytes());
+    request.extend_from_slice(&FLAGS_QUERY.to_be_bytes());
+    // one question, and no answer, authority or additional records
+    request.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+
+    for label in name.trim_end_matches('.').split('.') {
+        match u8::try_from(label.len()) {
+            Ok(length @ 1..=63) => {
+                request.push(length);
+                request.extend_from_slice(label.as_bytes());
+            }
+            _ => anyhow::bail!("invalid DNS name: {}", name),
+        }
+    }
+
+    request.push(0);
+    request.extend_from_slice(&query_type.to_be_bytes());
+    request.extend_from_slice(&class.to_be_bytes());
+
+    Ok(request)
+}
+
+/// Reads the addresses from the answer to a query: those of A and AAAA records, and TXT records that hold just an
+/// address. `None` if the message is not a response to this query.
+pub fn parse(message: &[u8], id: u16) -> anyhow::Result<Option<Vec<IpAddr>>> {
+    if message.len() < HEADER_LEN || read_u16(message, 0)? != id || read_u16(message, 2)? & FLAG_RESPONSE == 0 {
+        return Ok(None);
+    }
+
+    let flags = read_u16(message, 2)?;
+
+    if flags & FLAG_TRUNCATED != 0 {
+        anyhow::bail!("DNS response was truncated");
+    }
+
+    match flags & RCODE_MASK {
+        0 => {}
+        2 => anyhow::bail!("DNS resolver failed to answer (SERVFAIL)"),
+        3 => anyhow::bail!("DNS name does not exist (NXDOMAIN)"),
+        5 => anyhow::bail!("DNS resolver refused to answer (REFUSED)"),
+        rcode => anyhow::bail!("DNS resolver responded with error code {}", rcode),
+    }
+
+    let mut offset = HEADER_LEN;
+
+    for _ in 0..read_u16(message, 4)? {
+        // the name, then its type and class
+        offset = skip_name(message, offset)? + 4;
+    }
+
+    let mut addresses = Vec::new();
+
+    for _ in 0..read_u16(message, 6)? {
+        offset = skip_name(message, offset)?;
+        let record_type = read_u16(message, offset)?;
+        // the class and TTL are not needed
+        let length = usize::from(read_u16(message, offset + 8)?);
+        let data = message.get(offset + 10..offset + 10 + length).context("DNS record is truncated")?;
+        offset += 10 + length;
+
+        match record_type {
+            TYPE_A => addresses.push(IpAddr::V4(Ipv4Addr::from(
+                <[u8; 4]>::try_from(data).ok().context("DNS A record has the wrong length")?,
+            ))),
+            TYPE_AAAA => addresses.push(IpAddr::V6(Ipv6Addr::from(
+                <[u8; 16]>::try_from(data).ok().context("DNS AAAA record has the wrong length")?,
+            ))),
+            // other TXT records may come along, e.g. Google's notes about EDNS client subnet
+            TYPE_TXT => addresses.extend(read_text(data)?.trim().parse::<IpAddr>().ok()),
+            _ => {}
+        }
+    }
+
+    Ok(Some(addresses))
+}
+
+/// Skips over a (possibly compressed) name, returning the offset just past it.
+fn skip_name(message: &[u8], mut offset: usize) -> anyhow::Result<usize> {
+    loop {
+        let length = *message.get(offset).context("DNS name is truncated")?;
+
+        match length {
+            0 => return Ok(offset + 1),
+            // a pointer to the rest of the name, elsewhere in the message
+            _ if length & 0xc0 == 0xc0 => return Ok(offset + 2),
+            _ => offset += 1 + usize::from(length),
+        }
+    }
+}
+
+/// Joins the character strings of a TXT record.
+fn read_text(mut data: &[u8]) -> anyhow::Result<String> {
+    let mut text = Vec::new();
+
+    while let Some((&length, rest)) = data.split_first() {
+        let length = usize::from(length);
+        text.extend_from_slice(rest.get(..length).context("DNS TXT record is truncated")?);
+        data = &rest[length..];
+    }
+
+    Ok(String::from_utf8_lossy(&text).into_owned())
+}
+
+fn read_u16(buffer: &[u8], offset: usize) -> anyhow::Result<u16> {
+    buffer
+        .get(offset..offset + 2)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u16::from_be_bytes)
+        .context("DNS message is truncated")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::ip::dns;
+    use crate::api::ip::query_class::QueryClass;
+    use crate::api::ip::query_type::QueryType;
+    use crate::api::ip::DnsQuery;
+    use anyhow::Context;
+    use std::convert::TryFrom;
+    use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+    use std::thread;
+    use std::time::Duration;
+
+    /// A response to `request` with this response code, whose answers (of a type, and its data) all point back at
+    /// the question's name.
+    fn response(request: &[u8], rcode: u8, answers: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut message = request[..2].to_vec();
+        message.extend_from_slice(&[0x81, 0x80 | rcode, 0, 1, 0, u8::try_from(answers.len()).unwrap(), 0, 0, 0, 0]);
+        message.extend_from_slice(&request[12..]);
+
+        let class = &request[request.len() - 2..];
+        for (record_type, data) in answers {
+            message.extend_from_slice(&[0xc0, 0x0c]);
+            message.extend_from_slice(&record_type.to_be_bytes());
+            message.extend_from_slice(class);
+            message.extend_from_slice(&300_u32.to_be_bytes());
+            message.extend_from_slice(&u16::try_from(data.len()).unwrap().to_be_bytes());
+            message.extend_from_slice(data);
+        }
+
+        message
+    }
+
+    /// A resolver on loopback that ignores the first `drop` queries, then answers TXT queries with `203.0.113.4`, A
+    /// queries with `203.0.113.5`, and anything else with NXDOMAIN.
+    fn resolver(drop: usize) -> anyhow::Result<String> {
+        let socket = UdpSocket::bind("127.0.0.1:0")?;
+        let address = socket.local_addr()?.to_string();
+
+        thread::spawn(move || {
+            let mut buffer = [0; 512];
+
+            for _ in 0..drop {
+                let _ = socket.recv_from(&mut buffer);
+            }
+
+            while let Ok((length, from)) = socket.recv_from(&mut buffer) {
+                let request = &buffer[..length];
+                let response = match request[length - 4..length - 2] {
+                    [0, 16] => response(request, 0, &[(16, b"\x0b203.0.113.4")]),
+                    [0, 1] => response(request, 0, &[(1, &[203, 0, 113, 5])]),
+                    _ => response(request, 3, &[]),
+                };
+
+                let _ = socket.send_to(&response, from);
+            }
+        });
+
+        Ok(address)
+    }
+
+    #[test]
+    fn parse() -> anyhow::Result<()> {
+        let request = dns::request(0x1234, "whoami.cloudflare.", 16, 3)?;
+        assert_eq!(request.len(), 12 + 19 + 4);
+
+        let ipv4 = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 4));
+        let ipv6: IpAddr = "2001:db8::4".parse()?;
+
+        let answers: [(u16, &[u8]); 4] = [
+            // split into two character strings
+            (16, b"\x07203.0.1\x0413.4"),
+            (16, b"\x15edns0-client-subnet 0"),
+            (5, b"\x00"),
+            (28, &[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4]),
+        ];
+        assert_eq!(dns::parse(&response(&request, 0, &answers), 0x1234)?, Some(vec![ipv4, ipv6]));
+
+        assert_eq!(dns::parse(&response(&request, 0, &[]), 0x4321)?, None);
+        assert_eq!(dns::parse(&request, 0x1234)?, None);
+        assert!(dns::parse(&response(&request, 3, &[]), 0x1234).is_err());
+        assert!(dns::parse(&response(&request, 0, &answers)[..60], 0x1234).is_err());
+
+        let mut truncated = response(&request, 0, &[]);
+        truncated[2] |= 0x02;
+        assert!(dns::parse(&truncated, 0x1234).is_err());
+
+        assert!(dns::request(1, "whoami..cloudflare", 16, 3).is_err());
+        assert!(dns::request(1, &"a".repeat(64), 1, 1).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn query() -> anyhow::Result<()> {
+        let local = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+        let timeout = Duration::from_secs(5);
+
+        let server = resolver(0)?;
+        let question = DnsQuery::new(&server, "whoami.cloudflare", Some(QueryType::Txt), QueryClass::Ch);
+        assert_eq!(
+            dns::query_with(&question, local, timeout, 1).context("failed to query")?,
+            [IpAddr::V4(Ipv4Addr::new(203, 0, 113, 4))]
+        );
+
+        // the first query is lost, so it must be sent again; the type defaults to A
+        let server = resolver(1)?;
+        let question = DnsQuery::new(&server, "myip.opendns.com", None, QueryClass::In);
+        assert_eq!(
+            dns::query_with(&question, local, timeout, 2).context("failed to retransmit")?,
+            [IpAddr::V4(Ipv4Addr::new(203, 0, 113, 5))]
+        );
+
+        let question = DnsQuery::new(&server, "myip.opendns.com", Some(QueryType::Aaaa), QueryClass::In);
+        assert!(dns::query_with(&question, local, timeout, 3).is_err());
+
+        let server = resolver(usize::MAX)?;
+        let question = DnsQuery::new(&server, "myip.opendns.com", None, QueryClass::In);
+        assert!(dns::query_with(&question, local, Duration::from_millis(200), 4).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use crate::api::ip::query_class::QueryClass;
+use crate::api::ip::query_type::QueryType;
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// A "whoami" question for a DNS resolver, which answers with the address the question came from.
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct DnsQuery {
+    /// `host`, `host:port` or an IP address; the port defaults to 53.
+    resolver: String,
+    name: String,
+    /// Defaults to A or AAAA, for the family being discovered.
+    #[serde(rename = "type")]
+    query_type: Option<QueryType>,
+    #[serde(default)]
+    class: QueryClass,
+}
+
+impl DnsQuery {
+    pub fn new(resolver: &str, name: &str, query_type: Option<QueryType>, class: QueryClass) -> Self {
+        Self { resolver: resolver.to_string(), name: name.to_string(), query_type, class }
+    }
+
+    pub fn resolver(&self) -> &str {
+        &self.resolver
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub const fn query_type(&self) -> Option<QueryType> {
+        self.query_type
+    }
+
+    pub const fn class(&self) -> QueryClass {
+        self.class
+    }
+}
+
+/// Parses `name[/type[/class]]@resolver`, e.g. `whoami.cloudflare/txt/ch@1.1.1.1` or `myip.opendns.com@208.67.222.222`.
+impl FromStr for DnsQuery {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (question, resolver) = s
+            .rsplit_once('@')
+            .filter(|(question, resolver)| !question.is_empty() && !resolver.is_empty())
+            .ok_or_else(|| format!("expected <name>[/<type>[/<class>]]@<resolver>; got: {}", s))?;
+
+        let mut parts = question.split('/');
+        let name = parts.next().unwrap_or_default();
+        let query_type = parts.next().map(str::parse).transpose()?;
+        let class = parts.next().map(str::parse).transpose()?.unwrap_or_default();
+
+        if name.is_empty() || parts.next().is_some() {
+            return Err(format!("expected <name>[/<type>[/<class>]]@<resolver>; got: {}", s));
+        }
+
+        Ok(Self::new(resolver, name, query_type, class))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::ip::query_class::QueryClass;
+    use crate::api::ip::query_type::QueryType;
+    use crate::api::ip::DnsQuery;
+
+    #[test]
+    fn from_str() {
+        assert_eq!(
+            "whoami.cloudflare/txt/ch@1.1.1.1".parse(),
+            Ok(DnsQuery::new("1.1.1.1", "whoami.cloudflare", Some(QueryType::Txt), QueryClass::Ch))
+        );
+        assert_eq!(
+            "myip.opendns.com@[2620:119:35::35]:53".parse(),
+            Ok(DnsQuery::new("[2620:119:35::35]:53", "myip.opendns.com", None, QueryClass::In))
+        );
+        assert!("myip.opendns.com".parse::<DnsQuery>().is_err());
+        assert!("@1.1.1.1".parse::<DnsQuery>().is_err());
+        assert!("whoami.cloudflare/txt/ch/extra@1.1.1.1".parse::<DnsQuery>().is_err());
+        assert!("whoami.cloudflare/mx@1.1.1.1".parse::<DnsQuery>().is_err());
+    }
+}
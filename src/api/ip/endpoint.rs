@@ -0,0 +1,46 @@
+use crate::api::ip::extract::Extract;
+use serde::{Deserialize, Deserializer};
+use std::time::Duration;
+
+/// How long to wait for a discovery endpoint to respond when not otherwise configured.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An HTTP service that responds with the caller's public IP address.
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct Endpoint {
+    url: String,
+    #[serde(default)]
+    extract: Extract,
+    #[serde(default, deserialize_with = "deserialize_timeout")]
+    timeout: Option<Duration>,
+}
+
+impl Endpoint {
+    pub fn new(url: &str, extract: Extract) -> Self {
+        Self { url: url.to_string(), extract, timeout: None }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub const fn extract(&self) -> &Extract {
+        &self.extract
+    }
+
+    pub const fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub const fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = Some(timeout);
+    }
+}
+
+/// Reads a timeout written as a human-readable duration, e.g. "5s".
+fn deserialize_timeout<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    let timeout = String::deserialize(deserializer)?;
+
+    humantime::parse_duration(&timeout).map(Some).map_err(serde::de::Error::custom)
+}
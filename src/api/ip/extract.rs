@@ -0,0 +1,91 @@
+use anyhow::Context;
+use serde::Deserialize;
+use std::str::FromStr;
+use ureq::serde_json::Value;
+
+/// How to pull the IP address out of the body of a discovery endpoint's response.
+#[derive(Deserialize, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Extract {
+    /// The body is just the address (surrounding whitespace is ignored).
+    #[default]
+    Plain,
+    /// The body is JSON, and the address is the string at this JSON pointer (e.g. `/ip`).
+    Json(String),
+    /// The body is made of `key=value` lines (e.g. Cloudflare's `/cdn-cgi/trace`), and the address is the value of
+    /// this key.
+    Key(String),
+}
+
+impl Extract {
+    pub fn apply(&self, body: &str) -> anyhow::Result<String> {
+        match self {
+            Self::Plain => Ok(body.trim().to_string()),
+            Self::Json(pointer) => {
+                let json: Value = ureq::serde_json::from_str(body).context("response is not valid JSON")?;
+
+                json.pointer(pointer)
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .with_context(|| format!("response has no string at JSON pointer `{}`", pointer))
+            }
+            Self::Key(key) => body
+                .lines()
+                .filter_map(|line| line.split_once('='))
+                .find(|(name, _)| name.trim() == key)
+                .map(|(_, value)| value.trim().to_string())
+                .with_context(|| format!("response has no `{}=` line", key)),
+        }
+    }
+}
+
+impl FromStr for Extract {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "plain" => Ok(Self::Plain),
+            Some(("json", pointer)) if pointer.is_empty() || pointer.starts_with('/') => {
+                Ok(Self::Json(pointer.to_string()))
+            }
+            Some(("key", key)) if !key.is_empty() => Ok(Self::Key(key.to_string())),
+            _ => Err(format!("expected plain, json:<pointer> or key:<key>; got: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::ip::Extract;
+    use anyhow::Context;
+
+    #[test]
+    fn from_str() {
+        assert_eq!("plain".parse(), Ok(Extract::Plain));
+        assert_eq!("json:/ip".parse(), Ok(Extract::Json("/ip".into())));
+        assert_eq!("key:ip".parse(), Ok(Extract::Key("ip".into())));
+        assert!("json:ip".parse::<Extract>().is_err());
+        assert!("key:".parse::<Extract>().is_err());
+        assert!("xml".parse::<Extract>().is_err());
+    }
+
+    #[test]
+    fn apply() -> anyhow::Result<()> {
+        let trace = include_str!("../../../resources/tests/ip/trace.txt");
+        let json = include_str!("../../../resources/tests/ip/ip.json");
+
+        assert_eq!(Extract::Plain.apply(" 198.51.100.4\n").context("failed to extract plain")?, "198.51.100.4");
+        assert_eq!(Extract::Key("ip".into()).apply(trace).context("failed to extract key")?, "198.51.100.4");
+        assert_eq!(Extract::Json("/ip".into()).apply(json).context("failed to extract JSON")?, "198.51.100.4");
+        assert_eq!(
+            Extract::Json("/client/address".into()).apply(json).context("failed to extract nested JSON")?,
+            "2001:db8::4"
+        );
+
+        assert!(Extract::Key("ip6".into()).apply(trace).is_err());
+        assert!(Extract::Json("/port".into()).apply(json).is_err());
+        assert!(Extract::Json("/ip".into()).apply(trace).is_err());
+
+        Ok(())
+    }
+}
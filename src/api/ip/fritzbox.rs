@@ -0,0 +1,281 @@
+use crate::api::ip::{digest, soap};
+use anyhow::Context;
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Where a FRITZ!Box answers TR-064 calls, when not otherwise configured.
+const DEFAULT_URL: &str = "http://fritz.box:49000";
+
+/// The environment variable the password is read from when the configuration has a username but no password.
+pub const PASSWORD_VAR: &str = "FRITZBOX_PASSWORD";
+
+/// A service that reports the WAN addresses, and the path it is controlled at.
+type Service = (&'static str, &'static str);
+
+/// TR-064 proper, which needs a login.
+const TR064_IP: Service = ("urn:dslforum-org:service:WANIPConnection:1", "/upnp/control/wanipconnection1");
+/// Reports the IPv4 address of DSL connections, which the IP service may not.
+const TR064_PPP: Service = ("urn:dslforum-org:service:WANPPPConnection:1", "/upnp/control/wanpppconn1");
+/// The same actions over UPnP IGD, which needs no login, but only answers when the FRITZ!Box is set to "transmit
+/// status information over UPnP".
+const IGD_IP: Service = ("urn:schemas-upnp-org:service:WANIPConnection:1", "/igdupnp/control/WANIPConn1");
+
+/// An AVM FRITZ!Box, to be asked for its WAN addresses over TR-064.
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct Fritzbox {
+    #[serde(default = "Fritzbox::default_url")]
+    url: String,
+    /// Logs in with HTTP Digest authentication; without one, the UPnP IGD service is asked instead.
+    username: Option<String>,
+    password: Option<String>,
+    /// Report the first address of the delegated IPv6 prefix instead of the FRITZ!Box's own IPv6 address, for
+    /// records with an `interface_id`.
+    #[serde(default)]
+    prefix: bool,
+}
+
+impl Default for Fritzbox {
+    fn default() -> Self {
+        Self::new(DEFAULT_URL)
+    }
+}
+
+impl Fritzbox {
+    pub fn new(url: &str) -> Self {
+        Self { url: url.to_string(), username: None, password: None, prefix: false }
+    }
+
+    fn default_url() -> String {
+        DEFAULT_URL.into()
+    }
+
+    pub fn username(&self) -> Option<&str> {
+        self.username.as_deref()
+    }
+
+    pub fn set_username(&mut self, username: &str) {
+        self.username = Some(username.to_string());
+    }
+
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+
+    /// Fills in a missing password from the environment, if there is a username to log in with.
+    pub fn resolve_password(&mut self, env: fn(&str) -> Option<String>) -> anyhow::Result<()> {
+        if self.username.is_some() && self.password.is_none() {
+            let password = env(PASSWORD_VAR).filter(|password| !password.is_empty()).with_context(|| {
+                format!("no password for the FRITZ!Box user; set `password` in the config, or ${}", PASSWORD_VAR)
+            })?;
+
+            self.password = Some(password);
+        }
+
+        Ok(())
+    }
+
+    pub const fn set_prefix(&mut self, prefix: bool) {
+        self.prefix = prefix;
+    }
+
+    /// Asks for the WAN IPv4 address; over TR-064, the PPP service is asked when the IP service has no address.
+    pub fn v4(&self, timeout: Duration) -> anyhow::Result<Ipv4Addr> {
+        let services = if self.username.is_some() { &[TR064_IP, TR064_PPP][..] } else { &[IGD_IP] };
+        let mut errors = Vec::new();
+
+        for service in services {
+            let address = self
+                .call(service, "GetExternalIPAddress", timeout)
+                .and_then(|response| read(&response, "NewExternalIPAddress", "IPv4 address"));
+
+            match address {
+                Ok(address) => return Ok(address),
+                Err(e) => errors.push(format!("{:#}", e)),
+            }
+        }
+
+        anyhow::bail!("the FRITZ!Box did not report its IPv4 address; {}", errors.join("; "))
+    }
+
+    /// Asks for the WAN IPv6 address, or the first address of the delegated prefix.
+    pub fn v6(&self, timeout: Duration) -> anyhow::Result<Ipv6Addr> {
+        let service = if self.username.is_some() { &TR064_IP } else { &IGD_IP };
+
+        if self.prefix {
+            read(&self.call(service, "X_AVM_DE_GetIPv6Prefix", timeout)?, "NewIPv6Prefix", "IPv6 prefix")
+        } else {
+            let response = self.call(service, "X_AVM_DE_GetExternalIPv6Address", timeout)?;
+
+            read(&response, "NewExternalIPv6Address", "IPv6 address")
+        }
+    }
+
+    /// Calls an action that takes no arguments, answering the Digest challenge if there is a username.
+    fn call(&self, &(service, path): &Service, action: &str, timeout: Duration) -> anyhow::Result<String> {
+        let url = format!("{}{}", self.url.trim_end_matches('/'), path);
+        let post = || ureq::post(&url).timeout(timeout);
+
+        let response = match (soap::send(post(), service, action, &[]), self.username()) {
+            (Err(ureq::Error::Status(401, response)), Some(username)) => {
+                let challenge = response
+                    .header("WWW-Authenticate")
+                    .with_context(|| format!("{} asked for a login, but sent no challenge", url))?;
+                let authorization = digest::Challenge::parse(challenge)?.authorization(
+                    username,
+                    self.password().unwrap_or_default(),
+                    "POST",
+                    path,
+                    &format!("{:016x}", fastrand::u64(..)),
+                );
+
+                soap::send(post().set("Authorization", &authorization), service, action, &[])
+            }
+            (response, _) => response,
+        };
+
+        soap::read(&url, action, response)
+    }
+}
+
+#[cfg(test)]
+impl Fritzbox {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn set_password(&mut self, password: &str) {
+        self.password = Some(password.to_string());
+    }
+
+    pub const fn prefix(&self) -> bool {
+        self.prefix
+    }
+}
+
+/// Reads an address from a response; the FRITZ!Box reports an empty or unspecified one while it is not connected.
+fn read<T>(response: &str, name: &str, what: &str) -> anyhow::Result<T>
+where
+    T: FromStr + Into<IpAddr> + Copy,
+{
+    let text = soap::text(response, name).with_context(|| format!("response has no {}", name))?;
+
+    match text.parse::<T>() {
+        Ok(address) if !address.into().is_unspecified() => Ok(address),
+        Ok(_) => anyhow::bail!("the FRITZ!Box has no {}; is it connected?", what),
+        Err(_) if text.is_empty() => anyhow::bail!("the FRITZ!Box has no {}; is it connected?", what),
+        Err(_) => anyhow::bail!("the FRITZ!Box reported an invalid {}: {}", what, text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::ip::digest::{self, Challenge};
+    use crate::api::ip::fritzbox::Fritzbox;
+    use crate::api::ip::soap::tests::server;
+    use anyhow::Context;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use std::time::Duration;
+
+    const CHALLENGE: &str = "Digest realm=\"F!Box SOAP-Auth\", nonce=\"4EA9FC4F9C1B1D3B\", algorithm=MD5, qop=\"auth\"";
+
+    /// A FRITZ!Box that only lets `ddns` (with the password `secret`) call TR-064, and whose UPnP IGD service answers
+    /// anyone, including AVM's IPv6 actions.
+    fn respond(request: &str) -> (u16, String, String) {
+        let path = request.split_whitespace().nth(1).unwrap_or_default();
+        let header = |name: &str| {
+            request.lines().find_map(|line| {
+                line.split_once(':').filter(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.trim())
+            })
+        };
+        let action = header("SOAPAction").unwrap_or_default();
+
+        if path.starts_with("/upnp/") {
+            let authorized = header("Authorization").is_some_and(|authorization| {
+                let params = digest::params(authorization.trim_start_matches("Digest "));
+                let param = |name: &str| params.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+
+                let expected = Challenge::parse(CHALLENGE).unwrap().authorization(
+                    "ddns",
+                    "secret",
+                    "POST",
+                    path,
+                    param("cnonce").unwrap_or_default(),
+                );
+                param("username") == Some("ddns") && param("uri") == Some(path) && authorization == expected
+            });
+
+            if !authorized {
+                return (401, format!("WWW-Authenticate: {}\r\n", CHALLENGE), String::new());
+            }
+        }
+
+        let body = if action.ends_with("#GetExternalIPAddress\"") {
+            include_str!("../../../resources/tests/ip/fritzbox/get_external_ip_address.xml")
+        } else if action.ends_with("#X_AVM_DE_GetExternalIPv6Address\"") {
+            include_str!("../../../resources/tests/ip/fritzbox/get_external_ipv6_address.xml")
+        } else if action.ends_with("#X_AVM_DE_GetIPv6Prefix\"") {
+            include_str!("../../../resources/tests/ip/fritzbox/get_ipv6_prefix.xml")
+        } else {
+            include_str!("../../../resources/tests/ip/fritzbox/fault.xml")
+        };
+
+        (200, String::new(), body.into())
+    }
+
+    fn fritzbox(url: &str, password: Option<&str>) -> Fritzbox {
+        let mut fritzbox = Fritzbox::new(url);
+
+        if let Some(password) = password {
+            fritzbox.set_username("ddns");
+            fritzbox.set_password(password);
+        }
+
+        fritzbox
+    }
+
+    #[test]
+    fn tr064() -> anyhow::Result<()> {
+        let url = server(respond)?;
+        let timeout = Duration::from_secs(5);
+
+        let mut fritzbox = fritzbox(&url, Some("secret"));
+        assert_eq!(fritzbox.v4(timeout).context("failed to log in")?, Ipv4Addr::new(203, 0, 113, 4));
+        assert_eq!(fritzbox.v6(timeout).context("failed to log in")?, Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+
+        fritzbox.set_prefix(true);
+        assert_eq!(
+            fritzbox.v6(timeout).context("failed to read prefix")?,
+            Ipv6Addr::new(0x2001, 0xdb8, 0xaa00, 0, 0, 0, 0, 0)
+        );
+
+        let fritzbox = self::fritzbox(&url, Some("wrong"));
+        assert!(fritzbox.v4(timeout).is_err());
+        assert!(fritzbox.v6(timeout).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn igd() -> anyhow::Result<()> {
+        let url = server(respond)?;
+        let timeout = Duration::from_secs(5);
+        let mut fritzbox = fritzbox(&format!("{}/", url), None);
+
+        assert_eq!(fritzbox.v4(timeout).context("failed to ask without login")?, Ipv4Addr::new(203, 0, 113, 4));
+        assert_eq!(
+            fritzbox.v6(timeout).context("failed to ask without login")?,
+            Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)
+        );
+
+        fritzbox.set_prefix(true);
+        assert_eq!(
+            fritzbox.v6(timeout).context("failed to read prefix without login")?,
+            Ipv6Addr::new(0x2001, 0xdb8, 0xaa00, 0, 0, 0, 0, 0)
+        );
+
+        Ok(())
+    }
+}
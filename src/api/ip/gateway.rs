@@ -0,0 +1,58 @@
+//! Finds the default gateway, to ask it for its WAN address.
+
+use std::net::Ipv4Addr;
+
+/// The IPv4 default gateway, from the kernel's routing table.
+// mocked
+#[cfg(all(target_os = "linux", not(tarpaulin_include)))]
+pub fn default() -> anyhow::Result<Ipv4Addr> {
+    use anyhow::Context;
+
+    let routes = std::fs::read_to_string("/proc/net/route").context("failed to read /proc/net/route")?;
+
+    parse(&routes).context("there is no IPv4 default route")
+}
+
+// mocked
+#[cfg(all(not(target_os = "linux"), not(tarpaulin_include)))]
+pub fn default() -> anyhow::Result<Ipv4Addr> {
+    anyhow::bail!("finding the default gateway is only supported on Linux")
+}
+
+/// Reads the gateway of the first default route in `/proc/net/route`, whose addresses are written as hexadecimal
+/// numbers in host byte order.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse(routes: &str) -> Option<Ipv4Addr> {
+    routes.lines().skip(1).find_map(|line| {
+        let fields: Vec<_> = line.split_whitespace().collect();
+
+        match fields.as_slice() {
+            [_, "00000000", gateway, ..] => {
+                u32::from_str_radix(gateway, 16).ok().map(|gateway| Ipv4Addr::from(gateway.to_ne_bytes()))
+            }
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::ip::gateway;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn parse() {
+        let gateway = u32::from_ne_bytes([192, 0, 2, 1]);
+        let routes = format!(
+            "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+             eth0\t{:08X}\t00000000\t0001\t0\t0\t0\t{:08X}\n\
+             eth0\t00000000\t{:08X}\t0003\t0\t0\t100\t00000000\n",
+            u32::from_ne_bytes([192, 0, 2, 0]),
+            u32::from_ne_bytes([255, 255, 255, 0]),
+            gateway
+        );
+
+        assert_eq!(gateway::parse(&routes), Some(Ipv4Addr::new(192, 0, 2, 1)));
+        assert_eq!(gateway::parse(routes.lines().take(2).collect::<Vec<_>>().join("\n").as_str()), None);
+    }
+}
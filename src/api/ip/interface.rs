@@ -0,0 +1,159 @@
+use crate::api::ip::address_flags::AddressFlags;
+use crate::api::ip::interface_address::InterfaceAddress;
+use crate::api::ip::preference::Preference;
+use crate::api::ip::prefix::Prefix;
+use crate::api::ip::scope::Scope;
+use serde::Deserialize;
+use std::net::IpAddr;
+
+/// A local network interface to take an address from, and which of its addresses are acceptable.
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct Interface {
+    name: String,
+    #[serde(default)]
+    scope: Scope,
+    prefix: Option<Prefix>,
+    #[serde(default)]
+    prefer: Preference,
+}
+
+impl Interface {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), scope: Scope::default(), prefix: None, prefer: Preference::default() }
+    }
+
+    pub const fn set_scope(&mut self, scope: Scope) {
+        self.scope = scope;
+    }
+
+    pub const fn set_prefix(&mut self, prefix: Prefix) {
+        self.prefix = Some(prefix);
+    }
+
+    pub const fn set_prefer(&mut self, prefer: Preference) {
+        self.prefer = prefer;
+    }
+
+    /// Picks the first address on this interface that `family` accepts, that is in the right scope and prefix, and
+    /// whose flags suit the preference; unusable and deprecated addresses are always skipped.
+    pub fn select<T>(&self, addresses: &[InterfaceAddress], family: fn(IpAddr) -> Option<T>) -> anyhow::Result<T> {
+        let mut addresses = addresses.iter().filter(|address| address.interface() == self.name).peekable();
+
+        if addresses.peek().is_none() {
+            anyhow::bail!("no network interface named {} (or it has no addresses)", self.name);
+        }
+
+        let mut skipped = Vec::new();
+
+        for address in addresses {
+            if Scope::of(address.address()) != self.scope
+                || !self.prefix.is_none_or(|prefix| prefix.contains(address.address()))
+            {
+                continue;
+            }
+
+            let Some(selected) = family(address.address()) else { continue };
+            let flags = address.flags();
+
+            if flags.is_usable()
+                && !flags.contains(AddressFlags::DEPRECATED)
+                && self.prefer.accepts(flags.contains(AddressFlags::TEMPORARY))
+            {
+                return Ok(selected);
+            }
+
+            skipped.push(format!("{} ({})", address.address(), flags));
+        }
+
+        let prefix = self.prefix.map(|prefix| format!(" in {}", prefix)).unwrap_or_default();
+
+        if skipped.is_empty() {
+            anyhow::bail!("no {}-scope address{} on {}", self.scope, prefix, self.name);
+        }
+
+        anyhow::bail!(
+            "no usable {}-scope address{} on {} (preferring {}); skipped: {}",
+            self.scope,
+            prefix,
+            self.name,
+            self.prefer,
+            skipped.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::ip::address_flags::AddressFlags;
+    use crate::api::ip::interface_address::InterfaceAddress;
+    use crate::api::ip::{Interface, Preference, Scope};
+    use anyhow::Context;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    fn v4(address: IpAddr) -> Option<Ipv4Addr> {
+        match address {
+            IpAddr::V4(address) => Some(address),
+            IpAddr::V6(_) => None,
+        }
+    }
+
+    fn v6(address: IpAddr) -> Option<Ipv6Addr> {
+        match address {
+            IpAddr::V6(address) => Some(address),
+            IpAddr::V4(_) => None,
+        }
+    }
+
+    fn addresses() -> Vec<InterfaceAddress> {
+        let none = AddressFlags::default();
+
+        [
+            ("lo", "127.0.0.1", none),
+            ("lo", "::1", none),
+            ("eth0", "fe80::1", none),
+            ("eth0", "198.51.100.4", none),
+            ("eth0", "2001:db8::6", AddressFlags::TENTATIVE),
+            ("eth0", "2001:db8::5", AddressFlags::TEMPORARY),
+            ("eth0", "2001:db8::4", AddressFlags::MANAGE_TEMP_ADDRESS),
+            ("eth0", "2001:db8:1::5", AddressFlags::TEMPORARY | AddressFlags::DEPRECATED),
+            ("eth0", "2001:db8:1::4", AddressFlags::STABLE_PRIVACY),
+        ]
+        .iter()
+        // cannot panic; the addresses above are all valid
+        .map(|&(interface, address, flags)| InterfaceAddress::new(interface, address.parse().unwrap(), flags))
+        .collect()
+    }
+
+    #[test]
+    fn select() -> anyhow::Result<()> {
+        let mut eth0 = Interface::new("eth0");
+
+        assert_eq!(eth0.select(&addresses(), v4).context("no IPv4 address")?, Ipv4Addr::new(198, 51, 100, 4));
+        assert_eq!(eth0.select(&addresses(), v6).context("no IPv6 address")?, "2001:db8::4".parse::<Ipv6Addr>()?);
+
+        eth0.set_prefix("2001:db8:1::/48".parse().map_err(anyhow::Error::msg)?);
+        assert_eq!(eth0.select(&addresses(), v6).context("no prefixed address")?, "2001:db8:1::4".parse::<Ipv6Addr>()?);
+        assert!(eth0.select(&addresses(), v4).is_err());
+
+        let mut eth0 = Interface::new("eth0");
+        eth0.set_scope(Scope::Link);
+        assert_eq!(eth0.select(&addresses(), v6).context("no link address")?, "fe80::1".parse::<Ipv6Addr>()?);
+
+        let mut eth0 = Interface::new("eth0");
+        eth0.set_prefer(Preference::Temporary);
+        assert_eq!(eth0.select(&addresses(), v6).context("no temporary address")?, "2001:db8::5".parse::<Ipv6Addr>()?);
+
+        eth0.set_prefix("2001:db8:1::/48".parse().map_err(anyhow::Error::msg)?);
+        assert!(eth0.select(&addresses(), v6).is_err());
+
+        let mut eth0 = Interface::new("eth0");
+        eth0.set_prefer(Preference::Any);
+        assert_eq!(eth0.select(&addresses(), v6).context("no address")?, "2001:db8::5".parse::<Ipv6Addr>()?);
+
+        assert!(Interface::new("lo").select(&addresses(), v4).is_err());
+        assert!(Interface::new("eth1").select(&addresses(), v6).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+use crate::api::ip::address_flags::AddressFlags;
+use crate::api::ip::reserved::Reserved;
+use crate::api::ip::scope::Scope;
+use std::net::IpAddr;
+
+/// An address assigned to a local network interface.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct InterfaceAddress {
+    interface: String,
+    address: IpAddr,
+    flags: AddressFlags,
+}
+
+impl InterfaceAddress {
+    pub fn new(interface: &str, address: IpAddr, flags: AddressFlags) -> Self {
+        Self { interface: interface.to_string(), address, flags }
+    }
+
+    pub fn interface(&self) -> &str {
+        &self.interface
+    }
+
+    pub const fn address(&self) -> IpAddr {
+        self.address
+    }
+
+    pub const fn flags(&self) -> AddressFlags {
+        self.flags
+    }
+
+    /// Whether the address can reach the internet, as far as the host can tell: it has global scope, and is in use.
+    /// A private IPv4 address counts, as it is usually behind NAT; a unique local IPv6 address does not, as nothing
+    /// translates it.
+    pub fn is_global(&self) -> bool {
+        Scope::of(self.address) == Scope::Global
+            && Reserved::of(self.address) != Some(Reserved::UniqueLocal)
+            && self.flags.is_usable()
+            && !self.flags.contains(AddressFlags::DEPRECATED)
+    }
+
+    /// Lists the IPv4 and IPv6 addresses of every local network interface, along with their flags, via rtnetlink.
+    // mocked
+    #[cfg(all(target_os = "linux", not(tarpaulin_include)))]
+    pub fn list() -> anyhow::Result<Vec<Self>> {
+        use crate::api::ip::netlink::{self, Message};
+        use anyhow::Context;
+        use nix::sys::socket::{self, MsgFlags};
+        use std::collections::hash_map::{Entry, HashMap};
+        use std::os::fd::AsRawFd;
+
+        let socket = netlink::open(0)?;
+        socket::send(socket.as_raw_fd(), &netlink::dump_request(1), MsgFlags::empty())
+            .context("failed to request network interface addresses")?;
+
+        let mut names = HashMap::new();
+        let mut addresses = Vec::new();
+        let mut buffer = vec![0; 32 * 1024];
+
+        loop {
+            let length = socket::recv(socket.as_raw_fd(), &mut buffer, MsgFlags::empty())
+                .context("failed to read network interface addresses")?;
+
+            for message in netlink::parse(&buffer[..length])? {
+                match message {
+                    Message::Address(address) => {
+                        let name = match names.entry(address.index) {
+                            Entry::Occupied(entry) => entry.into_mut(),
+                            Entry::Vacant(entry) => entry.insert(netlink::interface_name(address.index)?),
+                        };
+
+                        addresses.push(Self::new(name, address.ip, address.flags));
+                    }
+                    Message::Done => return Ok(addresses),
+                    Message::Removed(_) | Message::Other => {}
+                }
+            }
+        }
+    }
+
+    /// Blocks, calling `changed` whenever a global-scope address is added to, changed on, or removed from one of these
+    /// interfaces (or when the kernel had to drop notifications, so one may have been missed), until it returns false.
+    // mocked
+    #[cfg(all(target_os = "linux", not(tarpaulin_include)))]
+    pub fn watch(interfaces: &[String], mut changed: impl FnMut() -> bool) -> anyhow::Result<()> {
+        use crate::api::ip::netlink::{self, Message};
+        use crate::api::ip::Scope;
+        use anyhow::Context;
+        use nix::errno::Errno;
+        use nix::sys::socket::{self, MsgFlags};
+        use std::os::fd::AsRawFd;
+
+        let socket = netlink::open(netlink::ADDRESS_GROUPS)?;
+        let mut buffer = vec![0; 32 * 1024];
+
+        loop {
+            let relevant = match socket::recv(socket.as_raw_fd(), &mut buffer, MsgFlags::empty()) {
+                Ok(length) => netlink::parse(&buffer[..length])?.iter().any(|message| match message {
+                    Message::Address(address) | Message::Removed(address) => {
+                        Scope::of(address.ip) == Scope::Global
+                            // the interface may already be gone, e.g. a PPP link going down; its return is what matters
+                            && netlink::interface_name(address.index).is_ok_and(|name| interfaces.contains(&name))
+                    }
+                    Message::Done | Message::Other => false,
+                }),
+                Err(Errno::ENOBUFS) => true,
+                Err(e) => return Err(e).context("failed to read network interface address changes"),
+            };
+
+            if relevant && !changed() {
+                return Ok(());
+            }
+        }
+    }
+
+    // mocked
+    #[cfg(all(not(target_os = "linux"), not(tarpaulin_include)))]
+    pub fn watch(_: &[String], _: impl FnMut() -> bool) -> anyhow::Result<()> {
+        anyhow::bail!("watching network interfaces is only supported on Linux")
+    }
+
+    /// Lists the IPv4 and IPv6 addresses of every local network interface, via `getifaddrs`; their flags are unknown.
+    // mocked
+    #[cfg(all(unix, not(target_os = "linux"), not(tarpaulin_include)))]
+    pub fn list() -> anyhow::Result<Vec<Self>> {
+        use anyhow::Context;
+
+        let addresses = nix::ifaddrs::getifaddrs().context("failed to list network interface addresses")?;
+
+        Ok(addresses
+            .filter_map(|entry| {
+                let storage = entry.address?;
+                let address = match (storage.as_sockaddr_in(), storage.as_sockaddr_in6()) {
+                    (Some(v4), _) => IpAddr::V4(v4.ip()),
+                    (_, Some(v6)) => IpAddr::V6(v6.ip()),
+                    _ => return None,
+                };
+
+                Some(Self::new(&entry.interface_name, address, AddressFlags::default()))
+            })
+            .collect())
+    }
+
+    // mocked
+    #[cfg(all(not(unix), not(tarpaulin_include)))]
+    pub fn list() -> anyhow::Result<Vec<Self>> {
+        anyhow::bail!("reading network interface addresses is only supported on Unix-like systems")
+    }
+}
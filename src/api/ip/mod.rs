@@ -0,0 +1,53 @@
+pub use client::Client;
+pub use discovery::Discovery;
+pub use dns_query::DnsQuery;
+pub use endpoint::Endpoint;
+pub use extract::Extract;
+pub use fritzbox::Fritzbox;
+pub use interface::Interface;
+pub use interface_address::InterfaceAddress;
+pub use preference::Preference;
+pub use prefix::Prefix;
+pub use reserved::Reserved;
+pub use router::Router;
+pub use router_protocol::RouterProtocol;
+pub use scope::Scope;
+pub use source::Source;
+
+mod address_flags;
+mod client;
+mod digest;
+mod discovery;
+mod dns;
+mod dns_query;
+mod endpoint;
+mod extract;
+mod fritzbox;
+mod gateway;
+mod interface;
+mod interface_address;
+mod md5;
+mod nat_pmp;
+#[cfg(target_os = "linux")]
+mod netlink;
+mod pcp;
+mod preference;
+mod prefix;
+mod query_class;
+mod query_type;
+mod reserved;
+mod router;
+mod router_protocol;
+mod scope;
+mod soap;
+mod source;
+mod stun;
+mod udp;
+mod upnp;
+
+#[cfg(test)]
+pub use client::tests;
+#[cfg(test)]
+pub use query_class::QueryClass;
+#[cfg(test)]
+pub use query_type::QueryType;
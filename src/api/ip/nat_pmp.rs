@@ -0,0 +1,103 @@
+//! Just enough of NAT-PMP (RFC 6886) to ask a router for its external address.
+
+use crate::api::ip::udp;
+use anyhow::Context;
+use std::convert::TryInto;
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
+/// The port routers listen for NAT-PMP (and PCP) requests on.
+pub const PORT: u16 = 5351;
+
+const VERSION: u8 = 0;
+const OPCODE_EXTERNAL_ADDRESS: u8 = 0;
+/// Responses have the request's opcode, plus 128.
+const OPCODE_RESPONSE: u8 = 128;
+const RESPONSE_LEN: usize = 12;
+
+/// Asks the router at `gateway` for its external IPv4 address, retransmitting the request until `timeout` runs out.
+pub fn query(gateway: &str, timeout: Duration) -> anyhow::Result<Ipv4Addr> {
+    udp::exchange("NAT-PMP gateway", gateway, PORT, IpAddr::V4(Ipv4Addr::UNSPECIFIED), timeout, |_| request(), parse)
+}
+
+pub fn request() -> Vec<u8> {
+    vec![VERSION, OPCODE_EXTERNAL_ADDRESS]
+}
+
+/// Reads the external address from a response; `None` if the message is not a response to an external address
+/// request.
+pub fn parse(message: &[u8]) -> anyhow::Result<Option<Ipv4Addr>> {
+    if message.get(..2) != Some(&[VERSION, OPCODE_RESPONSE + OPCODE_EXTERNAL_ADDRESS]) {
+        return Ok(None);
+    }
+
+    let octets: [u8; 4] = message
+        .get(8..RESPONSE_LEN)
+        .and_then(|octets| octets.try_into().ok())
+        .context("NAT-PMP response is truncated")?;
+
+    match u16::from_be_bytes([message[2], message[3]]) {
+        0 => Ok(Some(Ipv4Addr::from(octets))),
+        2 => anyhow::bail!("NAT-PMP is disabled on the gateway (not authorized)"),
+        3 => anyhow::bail!("the gateway has no external address yet (network failure)"),
+        code => anyhow::bail!("NAT-PMP gateway responded with result code {}", code),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::ip::nat_pmp;
+    use anyhow::Context;
+    use std::net::{Ipv4Addr, UdpSocket};
+    use std::thread;
+    use std::time::Duration;
+
+    /// A NAT-PMP response with this result code and external address.
+    fn response(code: u16, address: Ipv4Addr) -> Vec<u8> {
+        let mut message = vec![0, 128];
+        message.extend_from_slice(&code.to_be_bytes());
+        message.extend_from_slice(&3600_u32.to_be_bytes());
+        message.extend_from_slice(&address.octets());
+        message
+    }
+
+    /// A NAT-PMP gateway on loopback that answers with `198.51.100.4`, after ignoring the first request.
+    fn gateway() -> anyhow::Result<String> {
+        let socket = UdpSocket::bind("127.0.0.1:0")?;
+        let address = socket.local_addr()?.to_string();
+
+        thread::spawn(move || {
+            let mut buffer = [0; 64];
+            let _ = socket.recv_from(&mut buffer);
+
+            while let Ok((length, from)) = socket.recv_from(&mut buffer) {
+                assert_eq!(buffer[..length], nat_pmp::request());
+                let _ = socket.send_to(&response(0, Ipv4Addr::new(198, 51, 100, 4)), from);
+            }
+        });
+
+        Ok(address)
+    }
+
+    #[test]
+    fn parse() -> anyhow::Result<()> {
+        let address = Ipv4Addr::new(198, 51, 100, 4);
+
+        assert_eq!(nat_pmp::parse(&response(0, address))?, Some(address));
+        assert_eq!(nat_pmp::parse(&nat_pmp::request())?, None);
+        assert!(nat_pmp::parse(&response(2, address)).is_err());
+        assert!(nat_pmp::parse(&response(0, address)[..10]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn query() -> anyhow::Result<()> {
+        assert_eq!(
+            nat_pmp::query(&gateway()?, Duration::from_secs(5)).context("failed to query")?,
+            Ipv4Addr::new(198, 51, 100, 4)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+//! Just enough of the Linux rtnetlink protocol to list interface addresses along with their flags (which
+//! `getifaddrs` leaves out), and to be told when they change.
+
+use crate::api::ip::address_flags::AddressFlags;
+use anyhow::Context;
+use std::convert::{TryFrom, TryInto};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+const NLMSG_HEADER_LEN: usize = 16;
+const IFADDRMSG_LEN: usize = 8;
+/// A `struct nlmsghdr` followed by a `struct ifaddrmsg`.
+const DUMP_REQUEST_LEN: u32 = 24;
+
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+const RTM_NEWADDR: u16 = 20;
+const RTM_DELADDR: u16 = 21;
+const RTM_GETADDR: u16 = 22;
+
+const NLM_F_REQUEST: u16 = 0x01;
+const NLM_F_DUMP: u16 = 0x300;
+
+/// The multicast groups that announce IPv4 and IPv6 address changes (`RTMGRP_IPV4_IFADDR | RTMGRP_IPV6_IFADDR`).
+pub const ADDRESS_GROUPS: u32 = 0x10 | 0x100;
+
+const IFA_ADDRESS: u16 = 1;
+const IFA_LOCAL: u16 = 2;
+const IFA_FLAGS: u16 = 8;
+
+const AF_INET: u8 = 2;
+const AF_INET6: u8 = 10;
+
+/// An address reported by the kernel, identified by the index of its interface.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Address {
+    pub index: u32,
+    pub ip: IpAddr,
+    pub flags: AddressFlags,
+}
+
+/// A single message read from a netlink socket.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Message {
+    /// An address that exists (in a dump), or was just added or changed (e.g. finished duplicate address detection).
+    Address(Address),
+    /// An address that was just removed.
+    Removed(Address),
+    Done,
+    Other,
+}
+
+/// Opens a netlink socket, subscribed to these multicast groups (if any).
+// mocked
+#[cfg(not(tarpaulin_include))]
+pub fn open(groups: u32) -> anyhow::Result<std::os::fd::OwnedFd> {
+    use nix::sys::socket::{self, AddressFamily, NetlinkAddr, SockFlag, SockProtocol, SockType};
+    use std::os::fd::AsRawFd;
+
+    let socket =
+        socket::socket(AddressFamily::Netlink, SockType::Raw, SockFlag::SOCK_CLOEXEC, SockProtocol::NetlinkRoute)
+            .context("failed to open netlink socket")?;
+    socket::bind(socket.as_raw_fd(), &NetlinkAddr::new(0, groups)).context("failed to bind netlink socket")?;
+
+    Ok(socket)
+}
+
+/// The name of the network interface with this index; fails if it has since been removed.
+// mocked
+#[cfg(not(tarpaulin_include))]
+pub fn interface_name(index: u32) -> anyhow::Result<String> {
+    let name =
+        nix::net::if_::if_indextoname(index).with_context(|| format!("failed to name network interface {}", index))?;
+
+    Ok(name.to_string_lossy().into_owned())
+}
+
+/// Builds a request to dump every address of every interface.
+pub fn dump_request(sequence: u32) -> Vec<u8> {
+    let mut request = Vec::new();
+
+    request.extend_from_slice(&DUMP_REQUEST_LEN.to_ne_bytes());
+    request.extend_from_slice(&RTM_GETADDR.to_ne_bytes());
+    request.extend_from_slice(&(NLM_F_REQUEST | NLM_F_DUMP).to_ne_bytes());
+    request.extend_from_slice(&sequence.to_ne_bytes());
+    request.extend_from_slice(&0_u32.to_ne_bytes());
+    // struct ifaddrmsg, with every field zeroed to match any address family and interface
+    request.extend_from_slice(&[0; IFADDRMSG_LEN]);
+
+    request
+}
+
+/// Splits a buffer read from a netlink socket into its messages.
+pub fn parse(buffer: &[u8]) -> anyhow::Result<Vec<Message>> {
+    let mut messages = Vec::new();
+    let mut rest = buffer;
+
+    while rest.len() >= NLMSG_HEADER_LEN {
+        let length = read_u32(rest, 0)? as usize;
+        let kind = read_u16(rest, 4)?;
+
+        if length < NLMSG_HEADER_LEN || length > rest.len() {
+            anyhow::bail!("netlink message has an invalid length: {}", length);
+        }
+
+        let payload = &rest[NLMSG_HEADER_LEN..length];
+
+        messages.push(match kind {
+            NLMSG_DONE => Message::Done,
+            NLMSG_ERROR => match read_u32(payload, 0)? {
+                0 => Message::Other,
+                // the payload is the negated errno
+                error => anyhow::bail!(
+                    "netlink request failed: {}",
+                    nix::errno::Errno::from_raw(i32::from_ne_bytes(error.to_ne_bytes()).wrapping_neg())
+                ),
+            },
+            RTM_NEWADDR => parse_address(payload)?.map_or(Message::Other, Message::Address),
+            RTM_DELADDR => parse_address(payload)?.map_or(Message::Other, Message::Removed),
+            _ => Message::Other,
+        });
+
+        rest = &rest[align(length).min(rest.len())..];
+    }
+
+    Ok(messages)
+}
+
+/// Reads a `struct ifaddrmsg` and its attributes; addresses outside of IPv4 and IPv6 are skipped.
+fn parse_address(payload: &[u8]) -> anyhow::Result<Option<Address>> {
+    let family = *payload.first().context("netlink address message is truncated")?;
+    let mut flags = u32::from(*payload.get(2).context("netlink address message is truncated")?);
+    let index = read_u32(payload, 4)?;
+
+    let mut address = None;
+    let mut local = None;
+    let mut attributes = payload.get(IFADDRMSG_LEN..).unwrap_or_default();
+
+    while attributes.len() >= 4 {
+        let length = usize::from(read_u16(attributes, 0)?);
+        let kind = read_u16(attributes, 2)?;
+
+        if length < 4 || length > attributes.len() {
+            anyhow::bail!("netlink attribute has an invalid length: {}", length);
+        }
+
+        let value = &attributes[4..length];
+
+        match kind {
+            IFA_ADDRESS => address = to_address(family, value),
+            IFA_LOCAL => local = to_address(family, value),
+            // the full set of flags; the one in the header only has room for the first 8
+            IFA_FLAGS => flags = read_u32(value, 0)?,
+            _ => {}
+        }
+
+        attributes = &attributes[align(length).min(attributes.len())..];
+    }
+
+    // for point-to-point links, IFA_ADDRESS is the address of the other end
+    Ok(local.or(address).map(|ip| Address { index, ip, flags: AddressFlags::new(flags) }))
+}
+
+fn to_address(family: u8, value: &[u8]) -> Option<IpAddr> {
+    match family {
+        AF_INET => <[u8; 4]>::try_from(value).ok().map(|octets| IpAddr::V4(Ipv4Addr::from(octets))),
+        AF_INET6 => <[u8; 16]>::try_from(value).ok().map(|octets| IpAddr::V6(Ipv6Addr::from(octets))),
+        _ => None,
+    }
+}
+
+const fn align(length: usize) -> usize {
+    (length + 3) & !3
+}
+
+fn read_u16(buffer: &[u8], offset: usize) -> anyhow::Result<u16> {
+    buffer
+        .get(offset..offset + 2)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u16::from_ne_bytes)
+        .context("netlink message is truncated")
+}
+
+fn read_u32(buffer: &[u8], offset: usize) -> anyhow::Result<u32> {
+    buffer
+        .get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_ne_bytes)
+        .context("netlink message is truncated")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::ip::address_flags::AddressFlags;
+    use crate::api::ip::netlink::{self, Address, Message};
+    use anyhow::Context;
+    use std::net::IpAddr;
+
+    /// An `RTM_NEWADDR` (20) or `RTM_DELADDR` (21) message for `2001:db8::4` on interface 2, with the given flags in
+    /// `IFA_FLAGS`.
+    fn address(kind: u16, flags: u32) -> Vec<u8> {
+        let mut message = Vec::new();
+        message.extend_from_slice(&(16_u32 + 8 + 20 + 8).to_ne_bytes());
+        message.extend_from_slice(&kind.to_ne_bytes());
+        message.extend_from_slice(&[0; 10]);
+        message.extend_from_slice(&[10, 64, 0, 0]);
+        message.extend_from_slice(&2_u32.to_ne_bytes());
+        message.extend_from_slice(&20_u16.to_ne_bytes());
+        message.extend_from_slice(&1_u16.to_ne_bytes());
+        message.extend_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4]);
+        message.extend_from_slice(&8_u16.to_ne_bytes());
+        message.extend_from_slice(&8_u16.to_ne_bytes());
+        message.extend_from_slice(&flags.to_ne_bytes());
+        message
+    }
+
+    #[test]
+    fn parse() -> anyhow::Result<()> {
+        let mut buffer = address(20, 0x801);
+        buffer.extend(address(21, 0));
+        buffer.extend_from_slice(&16_u32.to_ne_bytes());
+        buffer.extend_from_slice(&3_u16.to_ne_bytes());
+        buffer.extend_from_slice(&[0; 10]);
+
+        let ip: IpAddr = "2001:db8::4".parse()?;
+        let flags = AddressFlags::TEMPORARY | AddressFlags::STABLE_PRIVACY;
+
+        assert_eq!(
+            netlink::parse(&buffer).context("failed to parse messages")?,
+            vec![
+                Message::Address(Address { index: 2, ip, flags }),
+                Message::Removed(Address { index: 2, ip, flags: AddressFlags::default() }),
+                Message::Done
+            ]
+        );
+
+        assert!(netlink::parse(&address(20, 0)[..40]).is_err());
+        assert_eq!(netlink::dump_request(1).len(), 24);
+
+        Ok(())
+    }
+}
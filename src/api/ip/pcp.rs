@@ -0,0 +1,214 @@
+//! Just enough of PCP (RFC 6887) to learn a router's external address, by briefly mapping a port.
+
+use crate::api::ip::nat_pmp::PORT;
+use crate::api::ip::udp;
+use anyhow::Context;
+use std::convert::TryInto;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+const VERSION: u8 = 2;
+const OPCODE_MAP: u8 = 1;
+/// Set on the opcode of responses.
+const RESPONSE: u8 = 0x80;
+const MESSAGE_LEN: usize = 60;
+
+const PROTOCOL_UDP: u8 = 17;
+/// How long the mapping is asked for; it is deleted straight away anyway.
+const LIFETIME: u32 = 60;
+
+/// Names of the result codes, from 1.
+const RESULTS: [&str; 13] = [
+    "UNSUPP_VERSION",
+    "NOT_AUTHORIZED",
+    "MALFORMED_REQUEST",
+    "UNSUPP_OPCODE",
+    "UNSUPP_OPTION",
+    "MALFORMED_OPTION",
+    "NETWORK_FAILURE",
+    "NO_RESOURCES",
+    "UNSUPP_PROTOCOL",
+    "USER_EX_QUOTA",
+    "CANNOT_PROVIDE_EXTERNAL",
+    "ADDRESS_MISMATCH",
+    "EXCESSIVE_REMOTE_PEERS",
+];
+
+/// A nonce, which ties a response to its request.
+pub type Nonce = [u8; 12];
+
+/// Asks the router at `gateway` for its external IPv4 address, retransmitting the request until `timeout` runs out.
+// mocked
+#[cfg(not(tarpaulin_include))]
+pub fn query(gateway: &str, timeout: Duration) -> anyhow::Result<Ipv4Addr> {
+    let nonce: Nonce = std::array::from_fn(|_| fastrand::u8(..));
+
+    query_with(gateway, timeout, nonce)
+}
+
+fn query_with(gateway: &str, timeout: Duration, nonce: Nonce) -> anyhow::Result<Ipv4Addr> {
+    let local = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+    let mut client = None;
+
+    let address = udp::exchange(
+        "PCP gateway",
+        gateway,
+        PORT,
+        local,
+        timeout,
+        |address| {
+            client = Some(address);
+            request(&nonce, address, LIFETIME)
+        },
+        |response| parse(response, &nonce),
+    )?;
+
+    // the mapping was only needed for its external address; the router removes it anyway once its lifetime is up
+    if let Some(client) = client {
+        let _ = udp::exchange(
+            "PCP gateway",
+            gateway,
+            PORT,
+            local,
+            Duration::from_secs(1),
+            |_| request(&nonce, client, 0),
+            |response| parse(response, &nonce),
+        );
+    }
+
+    address.to_ipv4_mapped().with_context(|| format!("PCP gateway reported a non-IPv4 external address: {}", address))
+}
+
+/// Builds a request to map the UDP port of `client`, for `lifetime` seconds (0 deletes the mapping).
+pub fn request(nonce: &Nonce, client: SocketAddr, lifetime: u32) -> Vec<u8> {
+    let client_ip = match client.ip() {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    };
+
+    let mut request = Vec::with_capacity(MESSAGE_LEN);
+
+    request.extend_from_slice(&[VERSION, OPCODE_MAP, 0, 0]);
+    request.extend_from_slice(&lifetime.to_be_bytes());
+    request.extend_from_slice(&client_ip.octets());
+    request.extend_from_slice(nonce);
+    request.extend_from_slice(&[PROTOCOL_UDP, 0, 0, 0]);
+    request.extend_from_slice(&client.port().to_be_bytes());
+    // no suggested external port or address
+    request.extend_from_slice(&0_u16.to_be_bytes());
+    request.extend_from_slice(&Ipv4Addr::UNSPECIFIED.to_ipv6_mapped().octets());
+
+    request
+}
+
+/// Reads the external address from a response to a map request; `None` if the message is not a response to this
+/// request.
+pub fn parse(message: &[u8], nonce: &Nonce) -> anyhow::Result<Option<Ipv6Addr>> {
+    match message {
+        // what NAT-PMP gateways answer to requests with a newer version
+        [0, _, 0, 1, ..] => anyhow::bail!("the gateway only supports NAT-PMP"),
+        [VERSION, opcode, ..] if *opcode == RESPONSE | OPCODE_MAP => {}
+        _ => return Ok(None),
+    }
+
+    if message.len() < MESSAGE_LEN {
+        anyhow::bail!("PCP response is truncated");
+    }
+
+    if &message[24..36] != nonce {
+        return Ok(None);
+    }
+
+    match message[3] {
+        0 => {}
+        code => anyhow::bail!(
+            "PCP gateway responded with {}",
+            RESULTS.get(usize::from(code) - 1).map_or_else(|| format!("result code {}", code), ToString::to_string)
+        ),
+    }
+
+    let octets: [u8; 16] = message[44..MESSAGE_LEN].try_into().context("PCP response is truncated")?;
+
+    Ok(Some(Ipv6Addr::from(octets)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::ip::pcp::{self, Nonce};
+    use anyhow::Context;
+    use std::net::{Ipv4Addr, UdpSocket};
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread;
+    use std::time::Duration;
+
+    const NONCE: Nonce = [12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1];
+
+    /// A response to `request` with this result code, mapping to `198.51.100.4`.
+    fn response(request: &[u8], code: u8) -> Vec<u8> {
+        let mut message = vec![2, 0x81, 0, code];
+        message.extend_from_slice(&request[4..8]);
+        message.extend_from_slice(&[0; 16]);
+        message.extend_from_slice(&request[24..42]);
+        message.extend_from_slice(&40000_u16.to_be_bytes());
+        message.extend_from_slice(&Ipv4Addr::new(198, 51, 100, 4).to_ipv6_mapped().octets());
+        message
+    }
+
+    /// A PCP gateway on loopback that answers every request, passing on the lifetime and internal port that were
+    /// asked for.
+    fn gateway() -> anyhow::Result<(String, Receiver<(u32, u16)>)> {
+        let socket = UdpSocket::bind("127.0.0.1:0")?;
+        let address = socket.local_addr()?.to_string();
+        let (lifetimes, received) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut buffer = [0; 128];
+
+            while let Ok((length, from)) = socket.recv_from(&mut buffer) {
+                let request = &buffer[..length];
+                assert_eq!(request.len(), 60);
+                assert_eq!(request[8..24], Ipv4Addr::LOCALHOST.to_ipv6_mapped().octets());
+
+                let _ = lifetimes.send((
+                    u32::from_be_bytes([request[4], request[5], request[6], request[7]]),
+                    u16::from_be_bytes([request[40], request[41]]),
+                ));
+                let _ = socket.send_to(&response(request, 0), from);
+            }
+        });
+
+        Ok((address, received))
+    }
+
+    #[test]
+    fn parse() -> anyhow::Result<()> {
+        let request = pcp::request(&NONCE, "192.0.2.2:5000".parse()?, 60);
+        let address = Ipv4Addr::new(198, 51, 100, 4).to_ipv6_mapped();
+
+        assert_eq!(pcp::parse(&response(&request, 0), &NONCE)?, Some(address));
+        assert_eq!(pcp::parse(&response(&request, 0), &[0; 12])?, None);
+        assert_eq!(pcp::parse(&request, &NONCE)?, None);
+        assert!(pcp::parse(&response(&request, 2), &NONCE).is_err());
+        assert!(pcp::parse(&response(&request, 0)[..50], &NONCE).is_err());
+        assert!(pcp::parse(&[0, 129, 0, 1, 0, 0, 0, 0], &NONCE).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn query() -> anyhow::Result<()> {
+        let (gateway, lifetimes) = gateway()?;
+
+        assert_eq!(
+            pcp::query_with(&gateway, Duration::from_secs(5), NONCE).context("failed to query")?,
+            Ipv4Addr::new(198, 51, 100, 4)
+        );
+
+        // the mapping is deleted straight away
+        let (lifetime, port) = lifetimes.recv_timeout(Duration::from_secs(1))?;
+        assert_eq!(lifetime, 60);
+        assert_eq!(lifetimes.recv_timeout(Duration::from_secs(1))?, (0, port));
+
+        Ok(())
+    }
+}
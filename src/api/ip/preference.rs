@@ -0,0 +1,50 @@
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// Which kind of IPv6 address to take from an interface, when it has several (e.g. with RFC 4941 privacy extensions).
+///
+/// Addresses that are tentative, failed duplicate address detection, or are deprecated are never picked.
+#[derive(Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Preference {
+    /// Anything but a temporary address, since those are replaced every day or so; the default.
+    #[default]
+    Stable,
+    /// Only a temporary address.
+    Temporary,
+    /// Whichever address comes first.
+    Any,
+}
+
+impl Preference {
+    pub const fn accepts(self, temporary: bool) -> bool {
+        match self {
+            Self::Stable => !temporary,
+            Self::Temporary => temporary,
+            Self::Any => true,
+        }
+    }
+}
+
+impl FromStr for Preference {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stable" => Ok(Self::Stable),
+            "temporary" => Ok(Self::Temporary),
+            "any" => Ok(Self::Any),
+            _ => Err(format!("expected stable, temporary or any; got: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for Preference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stable => write!(f, "stable"),
+            Self::Temporary => write!(f, "temporary"),
+            Self::Any => write!(f, "any"),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// A network in CIDR notation, e.g. `2001:db8::/32`.
+#[derive(Deserialize, Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[serde(try_from = "String")]
+pub struct Prefix {
+    network: IpAddr,
+    length: u8,
+}
+
+impl Prefix {
+    /// Whether an address is inside this network; never true for an address of the other family.
+    pub fn contains(self, address: IpAddr) -> bool {
+        match (self.network, address) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(self.length)).unwrap_or(0);
+                u32::from(network) & mask == u32::from(address) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(self.length)).unwrap_or(0);
+                u128::from(network) & mask == u128::from(address) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Prefix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected a network such as 192.0.2.0/24 or 2001:db8::/32; got: {}", s);
+
+        let (network, length) = s.split_once('/').ok_or_else(invalid)?;
+        let network = IpAddr::from_str(network).map_err(|_| invalid())?;
+        let length = u8::from_str(length).map_err(|_| invalid())?;
+
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        if length > max {
+            return Err(invalid());
+        }
+
+        Ok(Self { network, length })
+    }
+}
+
+impl TryFrom<String> for Prefix {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::from_str(&s)
+    }
+}
+
+impl std::fmt::Display for Prefix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.network, self.length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::ip::Prefix;
+    use std::net::IpAddr;
+
+    fn ip(s: &str) -> IpAddr {
+        // cannot panic; the tests only pass valid addresses
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn contains() -> Result<(), String> {
+        let v6: Prefix = "2001:db8::/32".parse()?;
+        assert!(v6.contains(ip("2001:db8:1::4")));
+        assert!(!v6.contains(ip("2001:db9::4")));
+        assert!(!v6.contains(ip("198.51.100.4")));
+
+        let v4: Prefix = "198.51.100.0/24".parse()?;
+        assert!(v4.contains(ip("198.51.100.4")));
+        assert!(!v4.contains(ip("198.51.101.4")));
+
+        let any: Prefix = "0.0.0.0/0".parse()?;
+        assert!(any.contains(ip("203.0.113.1")));
+
+        assert!("198.51.100.0/33".parse::<Prefix>().is_err());
+        assert!("2001:db8::".parse::<Prefix>().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// The class of a DNS query.
+#[derive(Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryClass {
+    /// The internet; the default.
+    #[default]
+    In,
+    /// Chaos, which some resolvers use for questions about themselves (e.g. `whoami.cloudflare`).
+    Ch,
+}
+
+impl QueryClass {
+    pub const fn code(self) -> u16 {
+        match self {
+            Self::In => 1,
+            Self::Ch => 3,
+        }
+    }
+}
+
+impl FromStr for QueryClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "in" => Ok(Self::In),
+            "ch" => Ok(Self::Ch),
+            _ => Err(format!("expected in or ch; got: {}", s)),
+        }
+    }
+}
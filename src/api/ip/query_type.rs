@@ -0,0 +1,35 @@
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// The type of record asked for in a DNS query.
+#[derive(Deserialize, Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryType {
+    A,
+    Aaaa,
+    /// A text record holding the address, e.g. Cloudflare's `whoami.cloudflare`.
+    Txt,
+}
+
+impl QueryType {
+    pub const fn code(self) -> u16 {
+        match self {
+            Self::A => 1,
+            Self::Txt => 16,
+            Self::Aaaa => 28,
+        }
+    }
+}
+
+impl FromStr for QueryType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "a" => Ok(Self::A),
+            "aaaa" => Ok(Self::Aaaa),
+            "txt" => Ok(Self::Txt),
+            _ => Err(format!("expected a, aaaa or txt; got: {}", s)),
+        }
+    }
+}
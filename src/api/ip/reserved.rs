@@ -0,0 +1,119 @@
+use std::net::IpAddr;
+
+/// A kind of address that is not reachable from the internet, so should never be published in public DNS.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Reserved {
+    Unspecified,
+    Loopback,
+    /// RFC 1918 (`10.0.0.0/8`, `172.16.0.0/12` and `192.168.0.0/16`).
+    Private,
+    /// Shared address space for carrier-grade NAT (RFC 6598; `100.64.0.0/10`).
+    CarrierGradeNat,
+    LinkLocal,
+    /// IPv6's answer to RFC 1918 (`fc00::/7`).
+    UniqueLocal,
+    /// Reserved for examples (`192.0.2.0/24`, `198.51.100.0/24`, `203.0.113.0/24` and `2001:db8::/32`).
+    Documentation,
+    Multicast,
+    Broadcast,
+}
+
+impl Reserved {
+    pub const fn of(address: IpAddr) -> Option<Self> {
+        match address {
+            IpAddr::V4(address) => {
+                let octets = address.octets();
+
+                if address.is_unspecified() {
+                    Some(Self::Unspecified)
+                } else if address.is_loopback() {
+                    Some(Self::Loopback)
+                } else if address.is_private() {
+                    Some(Self::Private)
+                } else if octets[0] == 100 && octets[1] & 0xc0 == 64 {
+                    Some(Self::CarrierGradeNat)
+                } else if address.is_link_local() {
+                    Some(Self::LinkLocal)
+                } else if address.is_documentation() {
+                    Some(Self::Documentation)
+                } else if address.is_multicast() {
+                    Some(Self::Multicast)
+                } else if address.is_broadcast() {
+                    Some(Self::Broadcast)
+                } else {
+                    None
+                }
+            }
+            IpAddr::V6(address) => {
+                let segments = address.segments();
+
+                if address.is_unspecified() {
+                    Some(Self::Unspecified)
+                } else if address.is_loopback() {
+                    Some(Self::Loopback)
+                } else if segments[0] & 0xffc0 == 0xfe80 {
+                    Some(Self::LinkLocal)
+                } else if segments[0] & 0xfe00 == 0xfc00 {
+                    Some(Self::UniqueLocal)
+                } else if segments[0] == 0x2001 && segments[1] == 0xdb8 {
+                    Some(Self::Documentation)
+                } else if address.is_multicast() {
+                    Some(Self::Multicast)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Reserved {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unspecified => write!(f, "unspecified"),
+            Self::Loopback => write!(f, "loopback"),
+            Self::Private => write!(f, "private (RFC 1918)"),
+            Self::CarrierGradeNat => write!(f, "carrier-grade NAT (RFC 6598)"),
+            Self::LinkLocal => write!(f, "link-local"),
+            Self::UniqueLocal => write!(f, "unique local"),
+            Self::Documentation => write!(f, "documentation"),
+            Self::Multicast => write!(f, "multicast"),
+            Self::Broadcast => write!(f, "broadcast"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::ip::Reserved;
+    use std::net::IpAddr;
+
+    fn reserved(s: &str) -> Option<Reserved> {
+        // cannot panic; the tests only pass valid addresses
+        Reserved::of(s.parse::<IpAddr>().unwrap())
+    }
+
+    #[test]
+    fn of() {
+        assert_eq!(reserved("127.0.0.1"), Some(Reserved::Loopback));
+        assert_eq!(reserved("::1"), Some(Reserved::Loopback));
+        assert_eq!(reserved("10.1.2.3"), Some(Reserved::Private));
+        assert_eq!(reserved("172.31.255.255"), Some(Reserved::Private));
+        assert_eq!(reserved("192.168.0.1"), Some(Reserved::Private));
+        assert_eq!(reserved("100.64.0.1"), Some(Reserved::CarrierGradeNat));
+        assert_eq!(reserved("100.127.255.254"), Some(Reserved::CarrierGradeNat));
+        assert_eq!(reserved("169.254.1.1"), Some(Reserved::LinkLocal));
+        assert_eq!(reserved("fe80::1"), Some(Reserved::LinkLocal));
+        assert_eq!(reserved("fd00::2"), Some(Reserved::UniqueLocal));
+        assert_eq!(reserved("203.0.113.4"), Some(Reserved::Documentation));
+        assert_eq!(reserved("2001:db8::4"), Some(Reserved::Documentation));
+        assert_eq!(reserved("224.0.0.1"), Some(Reserved::Multicast));
+        assert_eq!(reserved("ff02::1"), Some(Reserved::Multicast));
+        assert_eq!(reserved("255.255.255.255"), Some(Reserved::Broadcast));
+        assert_eq!(reserved("0.0.0.0"), Some(Reserved::Unspecified));
+
+        assert_eq!(reserved("100.128.0.1"), None);
+        assert_eq!(reserved("172.32.0.1"), None);
+        assert_eq!(reserved("2606:4700:4700::1111"), None);
+    }
+}
@@ -0,0 +1,67 @@
+use crate::api::ip::router_protocol::RouterProtocol;
+use crate::api::ip::{gateway, nat_pmp, pcp, upnp};
+use anyhow::Context;
+use serde::Deserialize;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+/// The local router, to be asked for its WAN address.
+#[derive(Deserialize, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct Router {
+    #[serde(default)]
+    protocol: RouterProtocol,
+    /// `host`, `host:port` or an IP address. PCP and NAT-PMP default to the default gateway (only found on Linux);
+    /// UPnP searches the whole network by default.
+    gateway: Option<String>,
+}
+
+impl Router {
+    pub const fn new(protocol: RouterProtocol, gateway: Option<String>) -> Self {
+        Self { protocol, gateway }
+    }
+
+    pub const fn protocol(&self) -> RouterProtocol {
+        self.protocol
+    }
+
+    pub fn gateway(&self) -> Option<&str> {
+        self.gateway.as_deref()
+    }
+
+    /// Asks the router for its WAN address with each of the protocols in turn, until one of them answers.
+    // mocked
+    #[cfg(not(tarpaulin_include))]
+    pub fn query(&self, timeout: Duration) -> anyhow::Result<Ipv4Addr> {
+        let mut errors = Vec::new();
+
+        for &protocol in self.protocol.candidates() {
+            let address = match protocol {
+                RouterProtocol::Pcp => self.gateway_or_default().and_then(|gateway| pcp::query(&gateway, timeout)),
+                RouterProtocol::NatPmp => {
+                    self.gateway_or_default().and_then(|gateway| nat_pmp::query(&gateway, timeout))
+                }
+                RouterProtocol::Upnp => upnp::query(self.gateway(), timeout),
+                RouterProtocol::Auto => continue,
+            };
+
+            match address {
+                Ok(address) => return Ok(address),
+                Err(e) => errors.push(format!("{}: {:#}", protocol, e)),
+            }
+        }
+
+        anyhow::bail!("the router did not report its WAN address; {}", errors.join("; "))
+    }
+
+    fn gateway_or_default(&self) -> anyhow::Result<String> {
+        self.gateway.clone().map_or_else(
+            || {
+                gateway::default()
+                    .map(|gateway| gateway.to_string())
+                    .context("failed to find the default gateway; configure the router's address")
+            },
+            Ok,
+        )
+    }
+}
@@ -0,0 +1,54 @@
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// How to ask the router for its WAN address.
+#[derive(Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum RouterProtocol {
+    /// Try PCP, then NAT-PMP, then UPnP IGD; the default.
+    #[default]
+    Auto,
+    /// The Port Control Protocol (RFC 6887).
+    Pcp,
+    /// NAT Port Mapping Protocol (RFC 6886), which PCP replaced.
+    NatPmp,
+    /// UPnP Internet Gateway Device, found with SSDP and asked over SOAP.
+    Upnp,
+}
+
+impl RouterProtocol {
+    /// The protocols to try, in order.
+    pub const fn candidates(self) -> &'static [Self] {
+        match self {
+            Self::Auto => &[Self::Pcp, Self::NatPmp, Self::Upnp],
+            Self::Pcp => &[Self::Pcp],
+            Self::NatPmp => &[Self::NatPmp],
+            Self::Upnp => &[Self::Upnp],
+        }
+    }
+}
+
+impl FromStr for RouterProtocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "pcp" => Ok(Self::Pcp),
+            "nat-pmp" => Ok(Self::NatPmp),
+            "upnp" => Ok(Self::Upnp),
+            _ => Err(format!("expected auto, pcp, nat-pmp or upnp; got: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for RouterProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Auto => write!(f, "auto"),
+            Self::Pcp => write!(f, "PCP"),
+            Self::NatPmp => write!(f, "NAT-PMP"),
+            Self::Upnp => write!(f, "UPnP IGD"),
+        }
+    }
+}
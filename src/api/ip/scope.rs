@@ -0,0 +1,52 @@
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// How far an interface address is reachable, as with `ip address show`.
+#[derive(Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Reachable from other networks; the only scope that makes sense to publish, so the default.
+    #[default]
+    Global,
+    /// Only reachable on the local link (`169.254.0.0/16` and `fe80::/10`).
+    Link,
+    /// Only reachable from this host (loopback).
+    Host,
+}
+
+impl Scope {
+    pub const fn of(address: IpAddr) -> Self {
+        match address {
+            IpAddr::V4(address) if address.is_loopback() => Self::Host,
+            IpAddr::V4(address) if address.is_link_local() => Self::Link,
+            IpAddr::V6(address) if address.is_loopback() => Self::Host,
+            // fe80::/10
+            IpAddr::V6(address) if address.segments()[0] & 0xffc0 == 0xfe80 => Self::Link,
+            _ => Self::Global,
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "global" => Ok(Self::Global),
+            "link" => Ok(Self::Link),
+            "host" => Ok(Self::Host),
+            _ => Err(format!("expected global, link or host; got: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Global => write!(f, "global"),
+            Self::Link => write!(f, "link"),
+            Self::Host => write!(f, "host"),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Just enough SOAP (as spoken by UPnP devices) to call an action and read the values in its response, without a full
+//! XML parser.
+
+use anyhow::Context;
+use std::fmt::Write;
+use ureq::{Request, Response};
+
+/// Calls `action` of `service`, with these arguments, returning the body of the response.
+pub fn call(request: Request, service: &str, action: &str, arguments: &[(&str, &str)]) -> anyhow::Result<String> {
+    let url = request.url().to_string();
+
+    read(&url, action, send(request, service, action, arguments))
+}
+
+/// Sends a call to `action` of `service`, leaving the response for the caller, e.g. to answer an authentication
+/// challenge before reading it with [`read`].
+pub fn send(
+    request: Request,
+    service: &str,
+    action: &str,
+    arguments: &[(&str, &str)],
+) -> Result<Response, ureq::Error> {
+    request
+        .set("Content-Type", "text/xml; charset=\"utf-8\"")
+        .set("SOAPAction", &format!("\"{}#{}\"", service, action))
+        .send_string(&envelope(service, action, arguments))
+}
+
+/// Reads the body of the response to a call to `action` at `url`, or the fault it responded with.
+pub fn read(url: &str, action: &str, response: Result<Response, ureq::Error>) -> anyhow::Result<String> {
+    match response {
+        Ok(response) => response.into_string().with_context(|| format!("failed to read response from {}", url)),
+        // faults come with a 500, and a description in the body
+        Err(ureq::Error::Status(code, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            let description = text(&body, "errorDescription").or_else(|| text(&body, "faultstring"));
+
+            anyhow::bail!("{} responded to {} with {}: {}", url, action, code, description.unwrap_or_default())
+        }
+        Err(ureq::Error::Transport(e)) => anyhow::bail!("transport error encountered when calling {}: {}", url, e),
+    }
+}
+
+pub fn envelope(service: &str, action: &str, arguments: &[(&str, &str)]) -> String {
+    let arguments = arguments.iter().fold(String::new(), |mut xml, (name, value)| {
+        let _ = write!(xml, "<{0}>{1}</{0}>", name, escape(value));
+        xml
+    });
+
+    format!(
+        "<?xml version=\"1.0\"?>\
+         <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+         s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+         <s:Body><u:{1} xmlns:u=\"{0}\">{2}</u:{1}></s:Body></s:Envelope>",
+        service, action, arguments
+    )
+}
+
+/// The text of the first element named `name` (whatever its namespace prefix), trimmed and unescaped.
+pub fn text(xml: &str, name: &str) -> Option<String> {
+    elements(xml, name).first().map(|content| unescape(content.trim()))
+}
+
+/// The raw content of every element named `name` (whatever its namespace prefix); elements nested in one of the same
+/// name are not found.
+pub fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let mut found = Vec::new();
+    let mut rest = xml;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+
+        let tag_end = rest.find(|c: char| c.is_whitespace() || c == '>' || c == '/').unwrap_or(rest.len());
+        let tag = &rest[..tag_end];
+        let local = tag.rsplit(':').next().unwrap_or_default();
+
+        if local != name {
+            continue;
+        }
+
+        let Some(open_end) = rest.find('>') else { break };
+
+        if rest[..open_end].ends_with('/') {
+            found.push("");
+            rest = &rest[open_end + 1..];
+            continue;
+        }
+
+        let content = &rest[open_end + 1..];
+        let Some(close) = content.find(&format!("</{}>", tag)) else { break };
+
+        found.push(&content[..close]);
+        rest = &content[close..];
+    }
+
+    found
+}
+
+fn escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn unescape(value: &str) -> String {
+    value.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&")
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::api::ip::soap;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// An HTTP server on loopback, returning its base URL; `respond` is given each raw request (head and body) and
+    /// returns the status, any extra header lines, and the body to respond with.
+    pub fn server(respond: fn(&str) -> (u16, String, String)) -> anyhow::Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}", listener.local_addr()?);
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+
+                // up to and including the blank line that ends the head
+                while reader.read_line(&mut request).unwrap_or(0) > 2 {}
+
+                let length = request
+                    .lines()
+                    .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(str::to_string))
+                    .and_then(|length| length.trim().parse().ok())
+                    .unwrap_or(0);
+                let mut body = vec![0; length];
+                let _ = reader.read_exact(&mut body);
+                request.push_str(&String::from_utf8_lossy(&body));
+
+                let (status, headers, body) = respond(&request);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {} Status\r\n{}Content-Type: text/xml\r\nContent-Length: {}\r\n\
+                     Connection: close\r\n\r\n{}",
+                    status,
+                    headers,
+                    body.len(),
+                    body
+                );
+            }
+        });
+
+        Ok(url)
+    }
+
+    #[test]
+    fn elements() {
+        let xml = "<root><s:Body><u:Response xmlns:u=\"urn:x\"><Value> a &amp;lt; b </Value><Empty/>\
+                   <Value>second</Value></u:Response></s:Body></root>";
+
+        assert_eq!(soap::text(xml, "Value"), Some("a &lt; b".into()));
+        assert_eq!(soap::elements(xml, "Value"), [" a &amp;lt; b ", "second"]);
+        assert_eq!(soap::text(xml, "Empty"), Some(String::new()));
+        assert_eq!(soap::text(xml, "Missing"), None);
+        assert!(soap::text(xml, "Response").unwrap_or_default().starts_with("<Value>"));
+    }
+
+    #[test]
+    fn envelope() {
+        let envelope = soap::envelope("urn:x", "Action", &[("NewValue", "a<b")]);
+
+        assert!(envelope.contains("<u:Action xmlns:u=\"urn:x\"><NewValue>a&lt;b</NewValue></u:Action>"));
+    }
+}
@@ -0,0 +1,24 @@
+use serde::Deserialize;
+
+/// Where an address family is discovered from.
+#[derive(Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// Ask the HTTP discovery providers.
+    #[default]
+    Http,
+    /// Read the address assigned to a local network interface.
+    Interface,
+    /// Use an address that is already known, e.g. passed on the command line.
+    Static,
+    /// Run a command, and read the address from what it prints.
+    Command,
+    /// Ask the STUN servers.
+    Stun,
+    /// Ask DNS resolvers a "whoami" question.
+    Dns,
+    /// Ask the local router for its WAN address (IPv4 only).
+    Router,
+    /// Ask an AVM FRITZ!Box over TR-064.
+    Fritzbox,
+}
@@ -0,0 +1,248 @@
+//! Just enough of STUN (RFC 5389) to ask a server which address and port it sees a Binding Request coming from.
+
+use crate::api::ip::udp;
+use anyhow::Context;
+use std::convert::TryInto;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+/// The port STUN servers listen on when the server address does not give one.
+pub const DEFAULT_PORT: u16 = 3478;
+
+const HEADER_LEN: usize = 20;
+const MAGIC_COOKIE: u32 = 0x2112_a442;
+
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_SUCCESS: u16 = 0x0101;
+const BINDING_ERROR: u16 = 0x0111;
+
+const MAPPED_ADDRESS: u16 = 0x0001;
+const XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+const FAMILY_IPV4: u8 = 0x01;
+const FAMILY_IPV6: u8 = 0x02;
+
+/// A transaction ID, which ties a response to its request.
+pub type Transaction = [u8; 12];
+
+/// Asks a STUN server for this host's public address, from a socket bound to `local` (the unspecified address of the
+/// family to discover); the request is retransmitted with exponential backoff until `timeout` runs out.
+// mocked
+#[cfg(not(tarpaulin_include))]
+pub fn query(server: &str, local: IpAddr, timeout: Duration) -> anyhow::Result<IpAddr> {
+    let transaction: Transaction = std::array::from_fn(|_| fastrand::u8(..));
+
+    query_with(server, local, timeout, transaction)
+}
+
+fn query_with(server: &str, local: IpAddr, timeout: Duration, transaction: Transaction) -> anyhow::Result<IpAddr> {
+    let request = request(&transaction);
+
+    udp::exchange(
+        "STUN server",
+        server,
+        DEFAULT_PORT,
+        local,
+        timeout,
+        |_| request,
+        |response| parse(response, &transaction),
+    )
+}
+
+/// Builds a Binding Request, with no attributes.
+pub fn request(transaction: &Transaction) -> Vec<u8> {
+    let mut request = Vec::with_capacity(HEADER_LEN);
+
+    request.extend_from_slice(&BINDING_REQUEST.to_be_bytes());
+    request.extend_from_slice(&0_u16.to_be_bytes());
+    request.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+    request.extend_from_slice(transaction);
+
+    request
+}
+
+/// Reads the mapped address from a Binding Success Response; `None` if the message is not a response to this
+/// transaction.
+pub fn parse(message: &[u8], transaction: &Transaction) -> anyhow::Result<Option<IpAddr>> {
+    if message.len() < HEADER_LEN || read_u32(message, 4)? != MAGIC_COOKIE || &message[8..HEADER_LEN] != transaction {
+        return Ok(None);
+    }
+
+    let kind = read_u16(message, 0)?;
+    let length = usize::from(read_u16(message, 2)?);
+    let mut attributes = message.get(HEADER_LEN..HEADER_LEN + length).context("STUN message is truncated")?;
+
+    match kind {
+        BINDING_SUCCESS => {}
+        BINDING_ERROR => anyhow::bail!("STUN server rejected the Binding Request"),
+        _ => anyhow::bail!("STUN server responded with an unexpected message type: {:#06x}", kind),
+    }
+
+    let mut mapped = None;
+
+    while attributes.len() >= 4 {
+        let kind = read_u16(attributes, 0)?;
+        let length = usize::from(read_u16(attributes, 2)?);
+        let value = attributes.get(4..4 + length).context("STUN attribute is truncated")?;
+
+        match kind {
+            XOR_MAPPED_ADDRESS => return read_address(value, Some(transaction)).map(Some),
+            // only sent by servers that predate RFC 5389, which do not XOR the address
+            MAPPED_ADDRESS => mapped = Some(read_address(value, None)?),
+            _ => {}
+        }
+
+        // attributes are padded to a multiple of 4 bytes
+        attributes = attributes.get((4 + length + 3) & !3..).unwrap_or_default();
+    }
+
+    mapped.map(Some).context("STUN response has no mapped address")
+}
+
+/// Reads a (XOR-)MAPPED-ADDRESS attribute; the port is not needed.
+fn read_address(value: &[u8], xor: Option<&Transaction>) -> anyhow::Result<IpAddr> {
+    let mut mask = [0; 16];
+    if let Some(transaction) = xor {
+        mask[..4].copy_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        mask[4..].copy_from_slice(transaction);
+    }
+
+    let family = *value.get(1).context("STUN address is truncated")?;
+    let address = value.get(4..).unwrap_or_default();
+    let unmask = |octets: &[u8]| octets.iter().zip(mask).map(|(octet, mask)| octet ^ mask).collect::<Vec<_>>();
+
+    match family {
+        FAMILY_IPV4 => {
+            let octets: [u8; 4] = unmask(address).try_into().ok().context("STUN IPv4 address is truncated")?;
+            Ok(IpAddr::V4(Ipv4Addr::from(octets)))
+        }
+        FAMILY_IPV6 => {
+            let octets: [u8; 16] = unmask(address).try_into().ok().context("STUN IPv6 address is truncated")?;
+            Ok(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => anyhow::bail!("STUN address has an unknown family: {}", family),
+    }
+}
+
+fn read_u16(buffer: &[u8], offset: usize) -> anyhow::Result<u16> {
+    buffer
+        .get(offset..offset + 2)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u16::from_be_bytes)
+        .context("STUN message is truncated")
+}
+
+fn read_u32(buffer: &[u8], offset: usize) -> anyhow::Result<u32> {
+    buffer
+        .get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_be_bytes)
+        .context("STUN message is truncated")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::ip::stun::{self, Transaction};
+    use anyhow::Context;
+    use std::convert::{TryFrom, TryInto};
+    use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+    use std::thread;
+    use std::time::Duration;
+
+    const TRANSACTION: Transaction = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+
+    /// A Binding Success Response to `transaction`, with an unknown attribute (which must be skipped) followed by
+    /// `address` as an XOR-MAPPED-ADDRESS.
+    fn response(transaction: &Transaction, address: IpAddr) -> Vec<u8> {
+        let (family, octets) = match address {
+            IpAddr::V4(address) => (1, address.octets().to_vec()),
+            IpAddr::V6(address) => (2, address.octets().to_vec()),
+        };
+
+        let mut mask = 0x2112_a442_u32.to_be_bytes().to_vec();
+        mask.extend_from_slice(transaction);
+
+        let mut attribute = vec![0x00, 0x20];
+        attribute.extend_from_slice(&u16::try_from(4 + octets.len()).unwrap().to_be_bytes());
+        attribute.extend_from_slice(&[0, family, 0x21 ^ 0x12, 0x12 ^ 0xa4]);
+        attribute.extend(octets.iter().zip(mask).map(|(octet, mask)| octet ^ mask));
+
+        let mut message = vec![0x01, 0x01];
+        message.extend_from_slice(&u16::try_from(8 + attribute.len()).unwrap().to_be_bytes());
+        message.extend_from_slice(&0x2112_a442_u32.to_be_bytes());
+        message.extend_from_slice(transaction);
+        // SOFTWARE, 3 bytes padded to 4
+        message.extend_from_slice(&[0x80, 0x22, 0, 3, b'd', b'n', b's', 0]);
+        message.extend(attribute);
+        message
+    }
+
+    /// A STUN server on loopback that ignores the first `drop` requests, then answers every other one with
+    /// `203.0.113.4`.
+    fn responder(drop: usize) -> anyhow::Result<String> {
+        let socket = UdpSocket::bind("127.0.0.1:0")?;
+        let address = socket.local_addr()?.to_string();
+
+        thread::spawn(move || {
+            let mut buffer = [0; 512];
+
+            for _ in 0..drop {
+                let _ = socket.recv_from(&mut buffer);
+            }
+
+            while let Ok((length, from)) = socket.recv_from(&mut buffer) {
+                let transaction: Transaction = buffer[8..20].try_into().unwrap();
+                assert_eq!(stun::request(&transaction), buffer[..length]);
+
+                let _ = socket.send_to(&response(&transaction, IpAddr::V4(Ipv4Addr::new(203, 0, 113, 4))), from);
+            }
+        });
+
+        Ok(address)
+    }
+
+    #[test]
+    fn parse() -> anyhow::Result<()> {
+        let ipv4: IpAddr = "203.0.113.4".parse()?;
+        let ipv6: IpAddr = "2001:db8:1234:5678:11:2233:4455:6677".parse()?;
+
+        assert_eq!(stun::parse(&response(&TRANSACTION, ipv4), &TRANSACTION)?, Some(ipv4));
+        assert_eq!(stun::parse(&response(&TRANSACTION, ipv6), &TRANSACTION)?, Some(ipv6));
+
+        let other = [0; 12];
+        assert_eq!(stun::parse(&response(&other, ipv4), &TRANSACTION)?, None);
+        assert_eq!(stun::parse(&stun::request(&TRANSACTION)[..8], &TRANSACTION)?, None);
+
+        let mut error = response(&TRANSACTION, ipv4);
+        error[1] = 0x11;
+        assert!(stun::parse(&error, &TRANSACTION).is_err());
+        assert!(stun::parse(&response(&TRANSACTION, ipv6)[..40], &TRANSACTION).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn query() -> anyhow::Result<()> {
+        let local = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+        let expected = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 4));
+
+        let server = responder(0)?;
+        assert_eq!(
+            stun::query_with(&server, local, Duration::from_secs(5), TRANSACTION).context("failed to query")?,
+            expected
+        );
+
+        // the first request is lost, so it must be sent again
+        let server = responder(1)?;
+        assert_eq!(
+            stun::query_with(&server, local, Duration::from_secs(5), TRANSACTION).context("failed to retransmit")?,
+            expected
+        );
+
+        let server = responder(usize::MAX)?;
+        assert!(stun::query_with(&server, local, Duration::from_millis(200), TRANSACTION).is_err());
+        assert!(stun::query_with("::1", local, Duration::from_millis(200), TRANSACTION).is_err());
+
+        Ok(())
+    }
+}
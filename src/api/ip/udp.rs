@@ -0,0 +1,93 @@
+//! Request/response exchanges over UDP, shared by the STUN, DNS and router sources.
+
+use anyhow::Context;
+use std::io::ErrorKind;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// How long to wait for a response before the first retransmit; doubled after each one (as in RFC 5389 section
+/// 7.2.1).
+const INITIAL_RTO: Duration = Duration::from_millis(500);
+
+/// Sends a request to `server` (called `what` in errors) from a socket bound to `local`, resending it with exponential
+/// backoff until `parse` accepts a response or `timeout` runs out.
+///
+/// `request` builds the request from the socket's local address. `parse` returns `None` for anything that is not a
+/// response to this request (e.g. a late response to an earlier one), which is then ignored. Responses to a request
+/// sent to a multicast group are taken from any address.
+pub fn exchange<T>(
+    what: &str,
+    server: &str,
+    default_port: u16,
+    local: IpAddr,
+    timeout: Duration,
+    request: impl FnOnce(SocketAddr) -> Vec<u8>,
+    mut parse: impl FnMut(&[u8]) -> anyhow::Result<Option<T>>,
+) -> anyhow::Result<T> {
+    let remote = resolve(what, server, default_port, local)?;
+    let socket = UdpSocket::bind(SocketAddr::new(local, 0)).context("failed to open UDP socket")?;
+    let multicast = remote.ip().is_multicast();
+
+    if !multicast {
+        socket.connect(remote).with_context(|| format!("failed to connect to {} {}", what, server))?;
+    }
+
+    let request = request(socket.local_addr().context("failed to read UDP socket address")?);
+
+    let mut buffer = [0; 4096];
+    let deadline = Instant::now() + timeout;
+    let mut rto = INITIAL_RTO;
+
+    loop {
+        // macOS and the BSDs refuse to send to an address on a connected socket, even the one it is connected to
+        let sent = if multicast { socket.send_to(&request, remote) } else { socket.send(&request) };
+        sent.with_context(|| format!("failed to send to {} {}", what, server))?;
+        let retransmit = (Instant::now() + rto).min(deadline);
+
+        loop {
+            let wait = retransmit.saturating_duration_since(Instant::now());
+            if wait.is_zero() {
+                break;
+            }
+
+            socket.set_read_timeout(Some(wait)).context("failed to set UDP socket timeout")?;
+
+            match socket.recv(&mut buffer) {
+                Ok(length) => {
+                    if let Some(response) = parse(&buffer[..length])
+                        .with_context(|| format!("unexpected response from {} {}", what, server))?
+                    {
+                        return Ok(response);
+                    }
+                }
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => break,
+                Err(e) => return Err(e).with_context(|| format!("failed to read from {} {}", what, server)),
+            }
+        }
+
+        if Instant::now() >= deadline {
+            anyhow::bail!("{} {} did not respond within {}", what, server, humantime::format_duration(timeout));
+        }
+
+        rto *= 2;
+    }
+}
+
+/// Resolves a server written as `host`, `host:port`, an IP address, or `[IPv6 address]:port`, to an address in the
+/// same family as `local`.
+fn resolve(what: &str, server: &str, default_port: u16, local: IpAddr) -> anyhow::Result<SocketAddr> {
+    let addresses: Vec<SocketAddr> = if let Ok(ip) = server.parse::<IpAddr>() {
+        vec![SocketAddr::new(ip, default_port)]
+    } else if server.contains(':') {
+        server.to_socket_addrs().with_context(|| format!("failed to resolve {} {}", what, server))?.collect()
+    } else {
+        (server, default_port)
+            .to_socket_addrs()
+            .with_context(|| format!("failed to resolve {} {}", what, server))?
+            .collect()
+    };
+
+    addresses.into_iter().find(|address| address.is_ipv4() == local.is_ipv4()).with_context(|| {
+        format!("{} {} has no {} address", what, server, if local.is_ipv4() { "IPv4" } else { "IPv6" })
+    })
+}
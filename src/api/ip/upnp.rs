@@ -0,0 +1,189 @@
+//! Just enough of UPnP Internet Gateway Device to ask a router for its external address: an SSDP search for the
+//! router, a fetch of its device description, then a `GetExternalIPAddress` call over SOAP.
+
+use crate::api::ip::{soap, udp};
+use anyhow::Context;
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
+const SSDP_GROUP: &str = "239.255.255.250";
+const SSDP_PORT: u16 = 1900;
+const SEARCH_TARGET: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+
+/// The services that can report the external address, in order of preference.
+const SERVICES: [&str; 3] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:2",
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+/// Asks the router for its external IPv4 address; it is searched for on the whole network, unless `gateway` says
+/// where it is.
+pub fn query(gateway: Option<&str>, timeout: Duration) -> anyhow::Result<Ipv4Addr> {
+    let location = udp::exchange(
+        "UPnP gateway",
+        gateway.unwrap_or(SSDP_GROUP),
+        SSDP_PORT,
+        IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        timeout,
+        |_| search_request().into_bytes(),
+        |response| Ok(parse_search(response)),
+    )?;
+
+    let description = ureq::get(&location)
+        .timeout(timeout)
+        .call()
+        .with_context(|| format!("failed to fetch UPnP device description from {}", location))?
+        .into_string()
+        .with_context(|| format!("failed to read UPnP device description from {}", location))?;
+
+    let (service, control) = control_url(&location, &description)?;
+    let response = soap::call(ureq::post(&control).timeout(timeout), service, "GetExternalIPAddress", &[])?;
+    let address = soap::text(&response, "NewExternalIPAddress").context("response has no NewExternalIPAddress")?;
+
+    match address.parse() {
+        // reported while the WAN connection is down
+        Ok(Ipv4Addr::UNSPECIFIED) => anyhow::bail!("the router has no external address; is it connected?"),
+        Ok(address) => Ok(address),
+        Err(_) if address.is_empty() => anyhow::bail!("the router has no external address; is it connected?"),
+        Err(_) => anyhow::bail!("the router reported an invalid external address: {}", address),
+    }
+}
+
+/// An SSDP search for Internet Gateway Devices.
+pub fn search_request() -> String {
+    format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}:{}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {}\r\n\r\n",
+        SSDP_GROUP, SSDP_PORT, SEARCH_TARGET
+    )
+}
+
+/// Reads the location of the device description from a response to a search; `None` if the message is not one.
+pub fn parse_search(message: &[u8]) -> Option<String> {
+    let message = String::from_utf8_lossy(message);
+    let mut lines = message.lines();
+
+    if !lines.next()?.starts_with("HTTP/1.1 200") {
+        return None;
+    }
+
+    lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("location"))
+        .map(|(_, location)| location.trim().to_string())
+}
+
+/// Finds the most preferred service that can report the external address in a device description, and its control
+/// URL.
+pub fn control_url(location: &str, description: &str) -> anyhow::Result<(&'static str, String)> {
+    let services = soap::elements(description, "service");
+    let base = soap::text(description, "URLBase").unwrap_or_else(|| location.to_string());
+
+    SERVICES
+        .iter()
+        .find_map(|&wanted| {
+            services
+                .iter()
+                .find(|service| soap::text(service, "serviceType").as_deref() == Some(wanted))
+                .and_then(|service| soap::text(service, "controlURL"))
+                .map(|control| (wanted, join(&base, &control)))
+        })
+        .with_context(|| format!("UPnP device at {} has no WAN connection service", location))
+}
+
+/// Resolves a URL from a device description against the description's own URL.
+fn join(base: &str, path: &str) -> String {
+    if path.starts_with("http://") || path.starts_with("https://") {
+        return path.to_string();
+    }
+
+    let authority = base.find("://").map_or(0, |scheme| scheme + 3);
+    let origin = base[authority..].find('/').map_or(base, |end| &base[..authority + end]);
+
+    format!("{}/{}", origin, path.trim_start_matches('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::ip::soap::tests::server;
+    use crate::api::ip::upnp;
+    use anyhow::Context;
+    use std::net::{Ipv4Addr, UdpSocket};
+    use std::thread;
+    use std::time::Duration;
+
+    const DESCRIPTION: &str = include_str!("../../../resources/tests/ip/igd.xml");
+
+    fn respond(request: &str) -> (u16, String, String) {
+        if request.starts_with("GET /igd.xml ") {
+            (200, String::new(), DESCRIPTION.into())
+        } else if request.starts_with("POST /upnp/control/WANIPConn1 ")
+            && request.contains("SOAPAction: \"urn:schemas-upnp-org:service:WANIPConnection:1#GetExternalIPAddress\"")
+        {
+            (200, String::new(), include_str!("../../../resources/tests/ip/get_external_ip_address.xml").into())
+        } else {
+            (404, String::new(), String::new())
+        }
+    }
+
+    /// An SSDP responder on loopback that points at `location`, after ignoring the first search.
+    fn responder(location: String) -> anyhow::Result<String> {
+        let socket = UdpSocket::bind("127.0.0.1:0")?;
+        let address = socket.local_addr()?.to_string();
+
+        thread::spawn(move || {
+            let mut buffer = [0; 512];
+            let _ = socket.recv_from(&mut buffer);
+
+            while let Ok((length, from)) = socket.recv_from(&mut buffer) {
+                assert!(buffer[..length].starts_with(b"M-SEARCH * HTTP/1.1\r\n"));
+
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=120\r\nST: {}\r\nLocation: {}\r\n\r\n",
+                    "urn:schemas-upnp-org:device:InternetGatewayDevice:1", location
+                );
+                let _ = socket.send_to(response.as_bytes(), from);
+            }
+        });
+
+        Ok(address)
+    }
+
+    #[test]
+    fn control_url() -> anyhow::Result<()> {
+        assert_eq!(
+            upnp::control_url("http://192.0.2.1:49000/igd.xml", DESCRIPTION)?,
+            ("urn:schemas-upnp-org:service:WANIPConnection:1", "http://192.0.2.1:49000/upnp/control/WANIPConn1".into())
+        );
+        assert!(upnp::control_url("http://192.0.2.1:49000/igd.xml", "<root></root>").is_err());
+
+        assert_eq!(upnp::join("http://192.0.2.1:49000", "ctl"), "http://192.0.2.1:49000/ctl");
+        assert_eq!(upnp::join("http://192.0.2.1/a/b.xml", "http://192.0.2.2/ctl"), "http://192.0.2.2/ctl");
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_search() {
+        assert_eq!(
+            upnp::parse_search(b"HTTP/1.1 200 OK\r\nLOCATION: http://192.0.2.1:49000/igd.xml\r\n\r\n"),
+            Some("http://192.0.2.1:49000/igd.xml".into())
+        );
+        assert_eq!(upnp::parse_search(upnp::search_request().as_bytes()), None);
+    }
+
+    #[test]
+    fn query() -> anyhow::Result<()> {
+        let gateway = responder(format!("{}/igd.xml", server(respond)?))?;
+
+        assert_eq!(
+            upnp::query(Some(&gateway), Duration::from_secs(5)).context("failed to query")?,
+            Ipv4Addr::new(198, 51, 100, 4)
+        );
+
+        let gateway = responder(format!("{}/missing.xml", server(respond)?))?;
+        assert!(upnp::query(Some(&gateway), Duration::from_secs(5)).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,419 @@
+use crate::api::cloudflare::{DnsRecord, DnsRecordType, Zone};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The name of the directory the state file is kept in, under the platform's state directory.
+const STATE_DIRECTORY: &str = "ddns-for-cloudflare";
+
+/// The name of the state file when not otherwise configured.
+const STATE_FILE: &str = "state.toml";
+
+/// Zones and DNS records already resolved against the Cloudflare API, and the addresses last published to them, so
+/// that repeated updates (i.e. in daemon mode, or across runs via the state file) do not need to look them up again.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+#[serde(from = "StateFile", into = "StateFile")]
+pub struct Cache {
+    path: Option<PathBuf>,
+    checked: u64,
+    fingerprint: String,
+    ipv4: Option<Ipv4Addr>,
+    ipv6: Option<Ipv6Addr>,
+    zones: HashMap<String, String>,
+    zones_listed: bool,
+    records: HashMap<(String, String, DnsRecordType), Vec<DnsRecord>>,
+    withdrawn: BTreeMap<(String, DnsRecordType), Withdrawal>,
+}
+
+/// How a DNS record was taken out of service while its address family had no address, and when; see
+/// [`crate::config::AbsentPolicy`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Withdrawal {
+    /// When it was withdrawn, in seconds since the Unix epoch.
+    since: u64,
+    /// The placeholder it was pointed at, or `None` if it was deleted.
+    parked: Option<IpAddr>,
+}
+
+impl Withdrawal {
+    pub const fn since(&self) -> u64 {
+        self.since
+    }
+
+    pub const fn parked(&self) -> Option<IpAddr> {
+        self.parked
+    }
+}
+
+/// The layout of the state file; values before tables, as TOML requires.
+#[derive(Serialize, Deserialize)]
+struct StateFile {
+    checked: u64,
+    fingerprint: String,
+    ipv4: Option<Ipv4Addr>,
+    ipv6: Option<Ipv6Addr>,
+    #[serde(default)]
+    zones_listed: bool,
+    #[serde(default)]
+    zones: BTreeMap<String, String>,
+    #[serde(default)]
+    records: Vec<StateRecords>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    withdrawn: Vec<StateWithdrawn>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StateRecords {
+    zone_id: String,
+    name: String,
+    #[serde(rename = "type")]
+    record_type: DnsRecordType,
+    records: Vec<DnsRecord>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StateWithdrawn {
+    name: String,
+    #[serde(rename = "type")]
+    record_type: DnsRecordType,
+    since: u64,
+    parked: Option<IpAddr>,
+}
+
+impl From<StateFile> for Cache {
+    fn from(file: StateFile) -> Self {
+        Self {
+            path: None,
+            checked: file.checked,
+            fingerprint: file.fingerprint,
+            ipv4: file.ipv4,
+            ipv6: file.ipv6,
+            zones: file.zones.into_iter().collect(),
+            zones_listed: file.zones_listed,
+            records: file
+                .records
+                .into_iter()
+                .map(|records| ((records.zone_id, records.name, records.record_type), records.records))
+                .collect(),
+            withdrawn: file
+                .withdrawn
+                .into_iter()
+                .map(|withdrawn| {
+                    let withdrawal = Withdrawal { since: withdrawn.since, parked: withdrawn.parked };
+                    ((withdrawn.name, withdrawn.record_type), withdrawal)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<Cache> for StateFile {
+    fn from(cache: Cache) -> Self {
+        let mut records: Vec<_> = cache
+            .records
+            .into_iter()
+            .map(|((zone_id, name, record_type), records)| StateRecords { zone_id, name, record_type, records })
+            .collect();
+        records.sort_by(|a, b| (&a.zone_id, &a.name, a.record_type).cmp(&(&b.zone_id, &b.name, b.record_type)));
+
+        Self {
+            checked: cache.checked,
+            fingerprint: cache.fingerprint,
+            ipv4: cache.ipv4,
+            ipv6: cache.ipv6,
+            zones_listed: cache.zones_listed,
+            zones: cache.zones.into_iter().collect(),
+            records,
+            withdrawn: cache
+                .withdrawn
+                .into_iter()
+                .map(|((name, record_type), withdrawal)| StateWithdrawn {
+                    name,
+                    record_type,
+                    since: withdrawal.since,
+                    parked: withdrawal.parked,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Cache {
+    /// Loads the cache from a state file; a missing or invalid file gives an empty cache, as everything in it can be
+    /// looked up again.
+    pub fn load(path: PathBuf) -> Self {
+        let mut cache = match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|e| {
+                eprintln!("Ignoring invalid state file {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(e) if e.kind() == ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                eprintln!("Ignoring unreadable state file {}: {}", path.display(), e);
+                Self::default()
+            }
+        };
+
+        cache.path = Some(path);
+        cache
+    }
+
+    /// Writes the cache back to the state file it was loaded from; does nothing if it was not loaded from one.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)
+                .with_context(|| format!("failed to create state directory: {}", directory.display()))?;
+        }
+
+        let contents = toml::to_string(self).context("failed to serialise state")?;
+        std::fs::write(path, contents).with_context(|| format!("failed to write state file: {}", path.display()))
+    }
+
+    /// Forgets everything looked up from the API, so that the next update checks every record again; withdrawn
+    /// records are remembered, as only this utility knows to restore them.
+    pub fn clear(&mut self) {
+        *self = Self { path: self.path.take(), withdrawn: std::mem::take(&mut self.withdrawn), ..Self::default() };
+    }
+
+    /// Whether the cache was built for a different configuration, or last checked against the API too long ago, to
+    /// be trusted.
+    pub fn is_stale(&self, fingerprint: &str, max_age: Duration, now: SystemTime) -> bool {
+        let checked = UNIX_EPOCH + Duration::from_secs(self.checked);
+
+        self.checked == 0
+            || self.fingerprint != fingerprint
+            || now.duration_since(checked).map_or(true, |age| age >= max_age)
+    }
+
+    /// Whether these are the addresses that were last published; a family without an address (i.e. one that was not
+    /// discovered this time) is not compared.
+    pub fn is_published(&self, ipv4: Option<Ipv4Addr>, ipv6: Option<Ipv6Addr>) -> bool {
+        (ipv4.is_none() || self.ipv4 == ipv4) && (ipv6.is_none() || self.ipv6 == ipv6)
+    }
+
+    /// Records that every record in the configuration with this fingerprint now points at these addresses; a family
+    /// without an address keeps the one last published.
+    pub fn set_published(
+        &mut self,
+        fingerprint: &str,
+        ipv4: Option<Ipv4Addr>,
+        ipv6: Option<Ipv6Addr>,
+        now: SystemTime,
+    ) {
+        // the age is measured from the last update made with an empty cache, i.e. one fully checked against the API
+        if self.checked == 0 {
+            self.checked = now.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+        }
+
+        self.fingerprint = fingerprint.to_string();
+        self.ipv4 = ipv4.or(self.ipv4);
+        self.ipv6 = ipv6.or(self.ipv6);
+    }
+
+    pub fn zone_id(&self, zone: &str) -> Option<&str> {
+        self.zones.get(&zone.to_ascii_lowercase()).map(String::as_str)
+    }
+
+    pub fn set_zone_id(&mut self, zone: &str, zone_id: &str) {
+        self.zones.insert(zone.to_ascii_lowercase(), zone_id.to_string());
+    }
+
+    /// Whether every zone visible to the API token has been cached, via [`Cache::set_zones`].
+    pub const fn zones_listed(&self) -> bool {
+        self.zones_listed
+    }
+
+    pub fn set_zones(&mut self, zones: &[Zone]) {
+        for zone in zones {
+            self.set_zone_id(zone.name(), zone.id());
+        }
+
+        self.zones_listed = true;
+    }
+
+    /// Finds the zone a domain belongs to, by walking up its labels (`a.b.example.com` → `b.example.com` →
+    /// `example.com` → `com`) until one matches a cached zone.
+    pub fn zone_id_for(&self, domain: &str) -> Option<&str> {
+        let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+        let mut candidate = domain.as_str();
+
+        loop {
+            if let Some(zone_id) = self.zones.get(candidate) {
+                return Some(zone_id);
+            }
+
+            candidate = candidate.split_once('.')?.1;
+        }
+    }
+
+    pub fn dns_records(&self, zone_id: &str, name: &str, record_type: DnsRecordType) -> Option<&[DnsRecord]> {
+        self.records.get(&(zone_id.to_string(), name.to_string(), record_type)).map(Vec::as_slice)
+    }
+
+    pub fn set_dns_records(&mut self, zone_id: &str, name: &str, record_type: DnsRecordType, records: Vec<DnsRecord>) {
+        self.records.insert((zone_id.to_string(), name.to_string(), record_type), records);
+    }
+
+    pub fn remove_dns_records(&mut self, zone_id: &str, name: &str, record_type: DnsRecordType) {
+        self.records.remove(&(zone_id.to_string(), name.to_string(), record_type));
+    }
+
+    pub fn withdrawal(&self, name: &str, record_type: DnsRecordType) -> Option<&Withdrawal> {
+        self.withdrawn.get(&(name.to_ascii_lowercase(), record_type))
+    }
+
+    /// Records that a DNS record was deleted, or parked at a placeholder, because its address family had no address;
+    /// the family's published address is forgotten, so that the record is restored as soon as there is one again.
+    pub fn set_withdrawn(&mut self, name: &str, record_type: DnsRecordType, parked: Option<IpAddr>, now: SystemTime) {
+        let since = now.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+        self.withdrawn.insert((name.to_ascii_lowercase(), record_type), Withdrawal { since, parked });
+
+        match record_type {
+            DnsRecordType::A => self.ipv4 = None,
+            DnsRecordType::AAAA => self.ipv6 = None,
+        }
+    }
+
+    /// Records that a withdrawn DNS record points at a real address again, returning how it had been withdrawn.
+    pub fn set_restored(&mut self, name: &str, record_type: DnsRecordType) -> Option<Withdrawal> {
+        self.withdrawn.remove(&(name.to_ascii_lowercase(), record_type))
+    }
+}
+
+/// Where the state file is kept when not otherwise configured: under `$XDG_STATE_HOME` (or its default of
+/// `~/.local/state`), or `%LOCALAPPDATA%` on Windows.
+pub fn default_path(env: fn(&str) -> Option<String>) -> Option<PathBuf> {
+    let non_empty = |key| env(key).filter(|value| !value.is_empty()).map(PathBuf::from);
+
+    let directory = non_empty("XDG_STATE_HOME")
+        .or_else(|| non_empty("HOME").map(|home| home.join(".local").join("state")))
+        .or_else(|| non_empty("LOCALAPPDATA"))?;
+
+    Some(directory.join(STATE_DIRECTORY).join(STATE_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::cloudflare::{DnsRecord, DnsRecordType, Zone};
+    use crate::cache::{default_path, Cache, Withdrawal};
+    use anyhow::Context;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn zone_id_for() {
+        let mut cache = Cache::default();
+        cache.set_zones(&[Zone::new("1", "example.co.uk"), Zone::new("2", "b.example.co.uk"), Zone::new("3", "uk")]);
+
+        assert_eq!(cache.zone_id_for("a.b.example.co.uk"), Some("2"));
+        assert_eq!(cache.zone_id_for("B.Example.co.uk."), Some("2"));
+        assert_eq!(cache.zone_id_for("c.example.co.uk"), Some("1"));
+        assert_eq!(cache.zone_id_for("example.co.uk"), Some("1"));
+        assert_eq!(cache.zone_id_for("example.uk"), Some("3"));
+        assert_eq!(cache.zone_id_for("example.com"), None);
+    }
+
+    #[test]
+    fn stale() {
+        let now = SystemTime::now();
+        let ipv4 = Some(Ipv4Addr::new(198, 51, 100, 4));
+        let mut cache = Cache::default();
+
+        assert!(cache.is_stale("config", Duration::from_secs(24 * 60 * 60), now));
+
+        cache.set_published("config", ipv4, None, now);
+
+        assert!(!cache.is_stale("config", Duration::from_secs(24 * 60 * 60), now + Duration::from_secs(60 * 60)));
+        assert!(cache.is_stale("config", Duration::from_secs(24 * 60 * 60), now + Duration::from_secs(24 * 60 * 60)));
+        assert!(cache.is_stale("other", Duration::from_secs(24 * 60 * 60), now));
+        assert!(cache.is_published(ipv4, None));
+        assert!(!cache.is_published(ipv4, Some("::1".parse().unwrap_or_else(|_| unreachable!()))));
+
+        cache.set_published("config", None, Some(Ipv6Addr::LOCALHOST), now);
+
+        assert!(cache.is_published(ipv4, Some(Ipv6Addr::LOCALHOST)));
+        assert!(cache.is_published(None, Some(Ipv6Addr::LOCALHOST)));
+        assert!(!cache.is_published(Some(Ipv4Addr::LOCALHOST), None));
+
+        cache.clear();
+
+        assert!(cache.is_stale("config", Duration::from_secs(24 * 60 * 60), now));
+        assert!(!cache.is_published(ipv4, None));
+    }
+
+    #[test]
+    fn withdrawn() {
+        let now = SystemTime::now();
+        let ipv6 = Some(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+        let mut cache = Cache::default();
+
+        cache.set_published("config", Some(Ipv4Addr::new(198, 51, 100, 4)), ipv6, now);
+        cache.set_withdrawn("Example.com", DnsRecordType::AAAA, None, now);
+
+        // the record is restored as soon as there is an address again, even the same one
+        assert!(!cache.is_published(None, ipv6));
+        assert!(cache.is_published(Some(Ipv4Addr::new(198, 51, 100, 4)), None));
+        assert_eq!(cache.withdrawal("example.com", DnsRecordType::AAAA).map(Withdrawal::parked), Some(None));
+        assert_eq!(cache.withdrawal("example.com", DnsRecordType::A), None);
+
+        cache.clear();
+        assert!(cache.withdrawal("example.com", DnsRecordType::AAAA).is_some());
+
+        assert!(cache.set_restored("example.com", DnsRecordType::AAAA).is_some());
+        assert_eq!(cache.withdrawal("example.com", DnsRecordType::AAAA), None);
+    }
+
+    #[test]
+    fn save_load() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!("ddns-for-cloudflare-{}", std::process::id())).join("state.toml");
+        let record =
+            DnsRecord::new("372e67954025e0ba6aaa6d586b9e0b59", false, IpAddr::V4(Ipv4Addr::LOCALHOST), 1, false, None);
+
+        let mut cache = Cache::load(path.clone());
+        assert_eq!(cache.zone_id("example.com"), None);
+
+        cache.set_zone_id("example.com", "023e105f4ecef8ad9ca31a8372d0c353");
+        cache.set_dns_records("023e105f4ecef8ad9ca31a8372d0c353", "example.com", DnsRecordType::A, vec![record]);
+        cache.set_published("config", Some(Ipv4Addr::LOCALHOST), None, SystemTime::now());
+        cache.set_withdrawn(
+            "example.com",
+            DnsRecordType::AAAA,
+            Some(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+            SystemTime::now(),
+        );
+        cache.save().context("failed to save state")?;
+
+        let loaded = Cache::load(path.clone());
+        std::fs::remove_dir_all(path.parent().context("state file has no directory")?)?;
+
+        assert_eq!(loaded, cache);
+
+        Ok(())
+    }
+
+    #[test]
+    fn default_paths() {
+        assert_eq!(
+            default_path(|key| (key == "XDG_STATE_HOME").then(|| "/state".to_string())),
+            Some(PathBuf::from("/state/ddns-for-cloudflare/state.toml"))
+        );
+        assert_eq!(
+            default_path(|key| match key {
+                "XDG_STATE_HOME" => Some(String::new()),
+                "HOME" => Some("/home/user".to_string()),
+                _ => None,
+            }),
+            Some(PathBuf::from("/home/user/.local/state/ddns-for-cloudflare/state.toml"))
+        );
+        assert_eq!(default_path(|_| None), None);
+    }
+}
@@ -0,0 +1,70 @@
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// What to do with the DNS records of an address family while there is no address to publish in it, e.g. after the
+/// host loses IPv6 connectivity; dual-stack clients would otherwise time out on the dead address before falling back.
+#[derive(Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum AbsentPolicy {
+    /// Leave them pointing at the last address published; this is the default.
+    #[default]
+    Keep,
+    /// Delete them, and create them again once there is an address.
+    Delete,
+    /// Point them at this placeholder address, and back once there is an address.
+    Park(IpAddr),
+}
+
+impl AbsentPolicy {
+    /// The address records are parked at, if they are to be parked rather than deleted.
+    pub const fn placeholder(&self) -> Option<IpAddr> {
+        match self {
+            Self::Keep | Self::Delete => None,
+            Self::Park(ip) => Some(*ip),
+        }
+    }
+}
+
+impl FromStr for AbsentPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "keep" => Ok(Self::Keep),
+            None if s == "delete" => Ok(Self::Delete),
+            Some(("park", ip)) => {
+                ip.parse().map(Self::Park).map_err(|_| format!("invalid placeholder address: {}", ip))
+            }
+            _ => Err(format!("expected keep, delete or park:<address>; got: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for AbsentPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Keep => write!(f, "keep"),
+            Self::Delete => write!(f, "delete"),
+            Self::Park(ip) => write!(f, "park:{}", ip),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::AbsentPolicy;
+    use std::net::{IpAddr, Ipv6Addr};
+
+    #[test]
+    fn from_str() {
+        assert_eq!("keep".parse(), Ok(AbsentPolicy::Keep));
+        assert_eq!("delete".parse(), Ok(AbsentPolicy::Delete));
+        assert_eq!(
+            "park:2001:db8::dead".parse(),
+            Ok(AbsentPolicy::Park(IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0xdead))))
+        );
+        assert!("park:".parse::<AbsentPolicy>().is_err());
+        assert!("remove".parse::<AbsentPolicy>().is_err());
+    }
+}
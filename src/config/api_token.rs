@@ -0,0 +1,154 @@
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+
+/// The environment variable the API token is read from when not otherwise provided.
+pub const ENV_VAR: &str = "CLOUDFLARE_API_TOKEN";
+
+/// The name of the `systemd` credential (`LoadCredential=`) the API token is read from when not otherwise provided.
+pub const CREDENTIAL: &str = "cloudflare-api-token";
+
+/// The places an API token can be given, in order of precedence; the first one set wins.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Sources<'a> {
+    pub arg: Option<&'a str>,
+    pub arg_file: Option<&'a Path>,
+    pub config: Option<&'a str>,
+    pub config_file: Option<&'a Path>,
+}
+
+impl Sources<'_> {
+    /// Resolves the API token, falling back to the environment and then the `systemd` credentials directory.
+    pub fn resolve(&self, env: fn(&str) -> Option<String>) -> anyhow::Result<String> {
+        if let Some(token) = self.arg {
+            return Ok(token.to_string());
+        }
+
+        if let Some(path) = self.arg_file {
+            return read(path);
+        }
+
+        if let Some(token) = self.config {
+            return Ok(token.to_string());
+        }
+
+        if let Some(path) = self.config_file {
+            return read(path);
+        }
+
+        if let Some(token) = env(ENV_VAR).filter(|token| !token.trim().is_empty()) {
+            return Ok(token.trim().to_string());
+        }
+
+        if let Some(directory) = env("CREDENTIALS_DIRECTORY") {
+            let path = PathBuf::from(directory).join(CREDENTIAL);
+
+            if path.exists() {
+                return read(&path);
+            }
+        }
+
+        anyhow::bail!(
+            "no API token provided; pass --api-token or --api-token-file, set `api_token` or `api_token_file` in the \
+             config, set ${}, or provide the `{}` systemd credential",
+            ENV_VAR,
+            CREDENTIAL
+        )
+    }
+}
+
+/// Reads an API token from a file, ignoring surrounding whitespace.
+fn read(path: &Path) -> anyhow::Result<String> {
+    let contents =
+        std::fs::read_to_string(path).with_context(|| format!("failed to read API token file: {}", path.display()))?;
+
+    warn_if_readable_by_others(path);
+
+    let token = contents.trim();
+    if token.is_empty() {
+        anyhow::bail!("API token file is empty: {}", path.display());
+    }
+
+    Ok(token.to_string())
+}
+
+#[cfg(unix)]
+fn warn_if_readable_by_others(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    if let Ok(metadata) = std::fs::metadata(path) {
+        if metadata.permissions().mode() & 0o044 != 0 {
+            eprintln!(
+                "Warning: API token file is readable by group and/or others; consider `chmod 600 {}`",
+                path.display()
+            );
+        }
+    }
+}
+
+#[cfg(not(unix))]
+const fn warn_if_readable_by_others(_: &Path) {}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::api_token::{Sources, CREDENTIAL, ENV_VAR};
+    use anyhow::Context;
+    use std::path::Path;
+
+    const TOKEN_FILE: &str = "resources/tests/config/api_token";
+
+    // Not an actual token; taken directly from the API documentation
+    const API_TOKEN: &str = "YQSn-xWAQiiEh9qM58wZNnyQS7FUdoqGIUAbrh7T";
+
+    fn mock_env_empty(_: &str) -> Option<String> {
+        None
+    }
+
+    fn mock_env_var(key: &str) -> Option<String> {
+        if key == ENV_VAR {
+            Some(format!("{}\n", API_TOKEN))
+        } else {
+            None
+        }
+    }
+
+    fn mock_env_credentials(key: &str) -> Option<String> {
+        if key == "CREDENTIALS_DIRECTORY" {
+            Some("resources/tests/config/credentials".to_string())
+        } else {
+            None
+        }
+    }
+
+    #[test]
+    fn resolve_precedence() -> anyhow::Result<()> {
+        let sources = Sources {
+            arg: Some("arg"),
+            arg_file: Some(Path::new(TOKEN_FILE)),
+            config: Some("config"),
+            config_file: Some(Path::new(TOKEN_FILE)),
+        };
+        assert_eq!(sources.resolve(mock_env_var).context("failed to resolve token")?, "arg");
+
+        let sources = Sources { arg: None, ..sources };
+        assert_eq!(sources.resolve(mock_env_var).context("failed to resolve token")?, API_TOKEN);
+
+        let sources = Sources { arg_file: None, ..sources };
+        assert_eq!(sources.resolve(mock_env_var).context("failed to resolve token")?, "config");
+
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_fallbacks() -> anyhow::Result<()> {
+        let sources = Sources::default();
+
+        assert_eq!(sources.resolve(mock_env_var).context("failed to resolve token from env")?, API_TOKEN);
+        assert_eq!(
+            sources.resolve(mock_env_credentials).context("failed to resolve token from credential")?,
+            API_TOKEN
+        );
+        assert!(sources.resolve(mock_env_empty).unwrap_err().to_string().contains(CREDENTIAL));
+
+        Ok(())
+    }
+}
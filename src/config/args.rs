@@ -0,0 +1,541 @@
+use crate::api::ip::{DnsQuery, Extract, Preference, Prefix, RouterProtocol, Scope};
+use crate::config::absent_policy::AbsentPolicy;
+use crate::config::family::Family;
+use crate::config::family_mode::FamilyMode;
+use crate::config::multiple_policy::MultiplePolicy;
+use argh::FromArgs;
+use std::path::{Path, PathBuf};
+
+/// A CLI utility to update the A and AAAA DNS records of a domain managed by Cloudflare, from the executing system's
+/// current public IP address (written in Rust).
+#[allow(clippy::struct_excessive_bools)] // command-line switches
+#[derive(FromArgs, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Args {
+    /// path to a TOML configuration file describing the zones and records to update
+    #[argh(option, short = 'c')]
+    config: Option<PathBuf>,
+
+    /// the name of the DNS zone the domain to update is in; derived from the domain if neither this nor --zone-id is
+    /// passed
+    #[argh(option, short = 'z')]
+    zone: Option<String>,
+
+    /// the ID of the DNS zone the domain to update is in; saves looking the zone up by name
+    #[argh(option)]
+    zone_id: Option<String>,
+
+    /// the name of the domain to update
+    #[argh(option, short = 'd')]
+    domain: Option<String>,
+
+    /// the API key with permissions to query and update the DNS record
+    #[argh(option, short = 'a')]
+    api_token: Option<String>,
+
+    /// path to a file containing the API token; preferred over --api-token, as it keeps the token out of the process
+    /// list and shell history
+    #[argh(option)]
+    api_token_file: Option<PathBuf>,
+
+    /// the legacy Global API Key, as an alternative to an API token; requires --email
+    #[argh(option)]
+    api_key: Option<String>,
+
+    /// the email address of the account the Global API Key belongs to
+    #[argh(option)]
+    email: Option<String>,
+
+    /// create any DNS record that does not exist yet, rather than failing
+    #[argh(switch)]
+    create_missing: bool,
+
+    /// the TTL (in seconds, or 1 for automatic) to set on every DNS record when it is updated or created
+    #[argh(option)]
+    ttl: Option<u32>,
+
+    /// whether every DNS record should be proxied through Cloudflare when it is updated or created ("true" or "false")
+    #[argh(option)]
+    proxied: Option<bool>,
+
+    /// the comment to set on every DNS record when it is updated or created
+    #[argh(option)]
+    comment: Option<String>,
+
+    /// a tag (e.g. "name:value") to set on every DNS record when it is updated or created; can be repeated, and
+    /// replaces any existing tags
+    #[argh(option)]
+    tag: Vec<String>,
+
+    /// what to do when a name has several A or AAAA records: "fail" (default), "all" to update every one,
+    /// "collapse" to update one and delete the rest, or "id:<id>" / "comment:<comment>" to update only those
+    #[argh(option)]
+    multiple: Option<MultiplePolicy>,
+
+    /// look up the DNS records and IP addresses, and print what would be changed without changing anything
+    #[argh(switch)]
+    dry_run: bool,
+
+    /// keep running, checking for IP address changes every --interval
+    #[argh(switch)]
+    daemon: bool,
+
+    /// how often to check for IP address changes in daemon mode, e.g. "30s", "5m" or "1h" (default: 5m)
+    #[argh(option)]
+    interval: Option<String>,
+
+    /// in daemon mode, also check as soon as an address is added to or removed from this network interface (Linux
+    /// only); can be repeated
+    #[argh(option)]
+    watch: Vec<String>,
+
+    /// how long a watched interface's addresses must stay unchanged before checking, so that a burst of changes
+    /// only causes one check, e.g. "500ms" (default: 2s)
+    #[argh(option)]
+    debounce: Option<String>,
+
+    /// check every DNS record against the Cloudflare API, even if the IP addresses have not changed since the last
+    /// check
+    #[argh(switch)]
+    force: bool,
+
+    /// how long the IP addresses can go unchanged before the DNS records are checked anyway, e.g. "12h" or "7d"
+    /// (default: 1d)
+    #[argh(option)]
+    max_age: Option<String>,
+
+    /// where to keep the state file, which remembers the last published IP addresses and the zone and record IDs
+    /// (default: $XDG_STATE_HOME/ddns-for-cloudflare/state.toml)
+    #[argh(option)]
+    state_file: Option<PathBuf>,
+
+    /// the URL of an HTTP service to fetch the public IPv4 address from; can be repeated, to fall back to the next
+    /// URL when one fails (default: ipify, then Cloudflare, then icanhazip)
+    #[argh(option)]
+    ipv4_url: Vec<String>,
+
+    /// how to find the IPv4 address in the response from --ipv4-url: "plain" (default) for just the address,
+    /// "json:<pointer>" for the string at a JSON pointer such as "/ip", or "key:<key>" for the value of a
+    /// "key=value" line, such as "key:ip" for Cloudflare's /cdn-cgi/trace; repeat once per --ipv4-url, or pass
+    /// once for all of them
+    #[argh(option)]
+    ipv4_extract: Vec<Extract>,
+
+    /// how many IPv4 discovery URLs (or STUN servers, or DNS queries) must agree on an address before it is trusted
+    /// (default: 1)
+    #[argh(option)]
+    ipv4_quorum: Option<usize>,
+
+    /// the URL of an HTTP service to fetch the public IPv6 address from; as for --ipv4-url
+    #[argh(option)]
+    ipv6_url: Vec<String>,
+
+    /// how to find the IPv6 address in the response from --ipv6-url; as for --ipv4-extract
+    #[argh(option)]
+    ipv6_extract: Vec<Extract>,
+
+    /// how many IPv6 discovery URLs (or STUN servers, or DNS queries) must agree on an address before it is trusted
+    /// (default: 1)
+    #[argh(option)]
+    ipv6_quorum: Option<usize>,
+
+    /// the IPv4 address to publish, instead of discovering it; "-" reads it from stdin
+    #[argh(option)]
+    ipv4: Option<String>,
+
+    /// the IPv6 address to publish, instead of discovering it; "-" reads it from stdin
+    #[argh(option)]
+    ipv6: Option<String>,
+
+    /// a command to run (with the system shell) that prints the IPv4 address to publish, instead of discovering it
+    #[argh(option)]
+    ipv4_command: Option<String>,
+
+    /// a command to run (with the system shell) that prints the IPv6 address to publish, instead of discovering it
+    #[argh(option)]
+    ipv6_command: Option<String>,
+
+    /// a STUN server ("host", "host:port" or an IP address) to ask for the public IPv4 address, instead of the IPv4
+    /// discovery URLs; can be repeated to fall back to the next server
+    #[argh(option)]
+    ipv4_stun: Vec<String>,
+
+    /// a STUN server to ask for the public IPv6 address; as for --ipv4-stun
+    #[argh(option)]
+    ipv6_stun: Vec<String>,
+
+    /// a DNS query whose answer is the public IPv4 address, as "<name>[/<type>[/<class>]]@<resolver>", e.g.
+    /// "whoami.cloudflare/txt/ch@1.1.1.1" or "myip.opendns.com@208.67.222.222"; can be repeated to fall back to
+    /// the next query
+    #[argh(option)]
+    ipv4_dns: Vec<DnsQuery>,
+
+    /// a DNS query whose answer is the public IPv6 address; as for --ipv4-dns
+    #[argh(option)]
+    ipv6_dns: Vec<DnsQuery>,
+
+    /// ask the local router for its WAN address, instead of the IPv4 discovery URLs, with "pcp", "nat-pmp", "upnp",
+    /// or "auto" to try each in turn
+    #[argh(option)]
+    ipv4_router: Option<RouterProtocol>,
+
+    /// the address of the router for --ipv4-router (default: the default gateway for PCP and NAT-PMP, which is only
+    /// found on Linux, and an SSDP search for UPnP)
+    #[argh(option)]
+    ipv4_router_gateway: Option<String>,
+
+    /// the URL of a FRITZ!Box's TR-064 interface (port 49000 of fritz.box, usually) to ask for the IPv4 address,
+    /// instead of the IPv4 discovery URLs
+    #[argh(option)]
+    ipv4_fritzbox: Option<String>,
+
+    /// the URL of a FRITZ!Box's TR-064 interface to ask for the IPv6 address; as for --ipv4-fritzbox
+    #[argh(option)]
+    ipv6_fritzbox: Option<String>,
+
+    /// publish the first address of the prefix delegated to --ipv6-fritzbox, instead of its own IPv6 address; for
+    /// records with an interface ID
+    #[argh(switch)]
+    ipv6_fritzbox_prefix: bool,
+
+    /// the user to log in to the FRITZ!Box as, with the password in the FRITZBOX_PASSWORD environment variable
+    /// (default: no login, which asks the FRITZ!Box's UPnP service instead)
+    #[argh(option)]
+    fritzbox_username: Option<String>,
+
+    /// the network interface to read the IPv4 address from, instead of asking the IPv4 discovery URLs
+    #[argh(option)]
+    ipv4_interface: Option<String>,
+
+    /// the scope of the address to read from --ipv4-interface: "global" (default), "link" or "host"
+    #[argh(option)]
+    ipv4_scope: Option<Scope>,
+
+    /// only read an address inside this network (in CIDR notation) from --ipv4-interface
+    #[argh(option)]
+    ipv4_prefix: Option<Prefix>,
+
+    /// the network interface to read the IPv6 address from, instead of asking the IPv6 discovery URLs
+    #[argh(option)]
+    ipv6_interface: Option<String>,
+
+    /// the scope of the address to read from --ipv6-interface; as for --ipv4-scope
+    #[argh(option)]
+    ipv6_scope: Option<Scope>,
+
+    /// only read an address inside this network (in CIDR notation) from --ipv6-interface
+    #[argh(option)]
+    ipv6_prefix: Option<Prefix>,
+
+    /// which kind of address to read from --ipv6-interface: "stable" (default; never a temporary privacy address),
+    /// "temporary" or "any"
+    #[argh(option)]
+    ipv6_prefer: Option<Preference>,
+
+    /// how many leading bits of the discovered IPv6 address make up the prefix that records with an interface ID are
+    /// built on (default: 64)
+    #[argh(option)]
+    ipv6_prefix_length: Option<u8>,
+
+    /// how long to wait for each IP discovery URL to respond, e.g. "5s" (default: 10s)
+    #[argh(option)]
+    ip_timeout: Option<String>,
+
+    /// publish a discovered address even if it is private, loopback, link-local, etc., as long as it is inside this
+    /// network (in CIDR notation); can be repeated
+    #[argh(option)]
+    allow_reserved: Vec<Prefix>,
+
+    /// only update the A record (IPv4)
+    #[argh(switch, short = '4')]
+    only_v4: bool,
+
+    /// only update the AAAA record (IPv6)
+    #[argh(switch, short = '6')]
+    only_v6: bool,
+
+    /// whether a failure to discover or publish the IPv4 address fails the run: "required" (default), "optional", or
+    /// "auto" to skip IPv4 quietly when no network interface has a global IPv4 address
+    #[argh(option)]
+    ipv4_mode: Option<FamilyMode>,
+
+    /// as for --ipv4-mode, but for the IPv6 address
+    #[argh(option)]
+    ipv6_mode: Option<FamilyMode>,
+
+    /// what to do with the A records while the host has no IPv4 address, which needs --ipv4-mode auto to tell: "keep"
+    /// (default), "delete" (they are created again once there is one), or "park:<address>" to point them at a
+    /// placeholder until then
+    #[argh(option)]
+    ipv4_absent: Option<AbsentPolicy>,
+
+    /// as for --ipv4-absent, but for the AAAA records and the IPv6 address
+    #[argh(option)]
+    ipv6_absent: Option<AbsentPolicy>,
+
+    #[argh(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(FromArgs, Clone, Debug, Eq, PartialEq, Hash)]
+#[argh(subcommand)]
+pub enum Command {
+    Hook(Hook),
+}
+
+/// update the records with the address passed in by a network hook (dhclient, dhcpcd, networkd-dispatcher or pppd's
+/// ip-up), instead of discovering it
+#[derive(FromArgs, Clone, Debug, Eq, PartialEq, Hash)]
+#[argh(subcommand, name = "hook")]
+pub struct Hook {
+    /// the arguments the hook was run with (pppd passes the local address as the fourth)
+    #[argh(positional)]
+    args: Vec<String>,
+}
+
+impl Hook {
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+}
+
+impl Args {
+    pub fn config(&self) -> Option<&Path> {
+        self.config.as_deref()
+    }
+
+    pub fn zone(&self) -> Option<&str> {
+        self.zone.as_deref()
+    }
+
+    pub fn zone_id(&self) -> Option<&str> {
+        self.zone_id.as_deref()
+    }
+
+    pub fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+
+    pub fn api_token(&self) -> Option<&str> {
+        self.api_token.as_deref()
+    }
+
+    pub fn api_token_file(&self) -> Option<&Path> {
+        self.api_token_file.as_deref()
+    }
+
+    pub fn api_key(&self) -> Option<&str> {
+        self.api_key.as_deref()
+    }
+
+    pub fn email(&self) -> Option<&str> {
+        self.email.as_deref()
+    }
+
+    pub const fn create_missing(&self) -> bool {
+        self.create_missing
+    }
+
+    pub const fn ttl(&self) -> Option<u32> {
+        self.ttl
+    }
+
+    pub const fn proxied(&self) -> Option<bool> {
+        self.proxied
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tag
+    }
+
+    pub const fn multiple(&self) -> Option<&MultiplePolicy> {
+        self.multiple.as_ref()
+    }
+
+    pub const fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub const fn daemon(&self) -> bool {
+        self.daemon
+    }
+
+    pub fn interval(&self) -> Option<&str> {
+        self.interval.as_deref()
+    }
+
+    pub fn watch(&self) -> &[String] {
+        &self.watch
+    }
+
+    pub fn debounce(&self) -> Option<&str> {
+        self.debounce.as_deref()
+    }
+
+    pub const fn force(&self) -> bool {
+        self.force
+    }
+
+    pub fn max_age(&self) -> Option<&str> {
+        self.max_age.as_deref()
+    }
+
+    pub fn state_file(&self) -> Option<&Path> {
+        self.state_file.as_deref()
+    }
+
+    pub fn urls(&self, family: Family) -> &[String] {
+        match family {
+            Family::V4 => &self.ipv4_url,
+            Family::V6 => &self.ipv6_url,
+        }
+    }
+
+    pub fn extracts(&self, family: Family) -> &[Extract] {
+        match family {
+            Family::V4 => &self.ipv4_extract,
+            Family::V6 => &self.ipv6_extract,
+        }
+    }
+
+    pub const fn quorum(&self, family: Family) -> Option<usize> {
+        match family {
+            Family::V4 => self.ipv4_quorum,
+            Family::V6 => self.ipv6_quorum,
+        }
+    }
+
+    pub fn address(&self, family: Family) -> Option<&str> {
+        match family {
+            Family::V4 => self.ipv4.as_deref(),
+            Family::V6 => self.ipv6.as_deref(),
+        }
+    }
+
+    pub fn command(&self, family: Family) -> Option<&str> {
+        match family {
+            Family::V4 => self.ipv4_command.as_deref(),
+            Family::V6 => self.ipv6_command.as_deref(),
+        }
+    }
+
+    pub fn stun(&self, family: Family) -> &[String] {
+        match family {
+            Family::V4 => &self.ipv4_stun,
+            Family::V6 => &self.ipv6_stun,
+        }
+    }
+
+    pub fn dns(&self, family: Family) -> &[DnsQuery] {
+        match family {
+            Family::V4 => &self.ipv4_dns,
+            Family::V6 => &self.ipv6_dns,
+        }
+    }
+
+    pub fn interface(&self, family: Family) -> Option<&str> {
+        match family {
+            Family::V4 => self.ipv4_interface.as_deref(),
+            Family::V6 => self.ipv6_interface.as_deref(),
+        }
+    }
+
+    pub const fn scope(&self, family: Family) -> Option<Scope> {
+        match family {
+            Family::V4 => self.ipv4_scope,
+            Family::V6 => self.ipv6_scope,
+        }
+    }
+
+    pub const fn prefix(&self, family: Family) -> Option<Prefix> {
+        match family {
+            Family::V4 => self.ipv4_prefix,
+            Family::V6 => self.ipv6_prefix,
+        }
+    }
+
+    /// Only IPv6 addresses can be temporary, so there is nothing to prefer for IPv4.
+    pub const fn prefer(&self, family: Family) -> Option<Preference> {
+        match family {
+            Family::V4 => None,
+            Family::V6 => self.ipv6_prefer,
+        }
+    }
+
+    pub const fn router(&self, family: Family) -> Option<RouterProtocol> {
+        match family {
+            Family::V4 => self.ipv4_router,
+            Family::V6 => None,
+        }
+    }
+
+    pub fn router_gateway(&self, family: Family) -> Option<&str> {
+        match family {
+            Family::V4 => self.ipv4_router_gateway.as_deref(),
+            Family::V6 => None,
+        }
+    }
+
+    pub fn fritzbox(&self, family: Family) -> Option<&str> {
+        match family {
+            Family::V4 => self.ipv4_fritzbox.as_deref(),
+            Family::V6 => self.ipv6_fritzbox.as_deref(),
+        }
+    }
+
+    /// Only IPv6 is delegated as a prefix.
+    pub const fn fritzbox_prefix(&self, family: Family) -> bool {
+        match family {
+            Family::V4 => false,
+            Family::V6 => self.ipv6_fritzbox_prefix,
+        }
+    }
+
+    pub fn fritzbox_username(&self) -> Option<&str> {
+        self.fritzbox_username.as_deref()
+    }
+
+    pub const fn ipv6_prefix_length(&self) -> Option<u8> {
+        self.ipv6_prefix_length
+    }
+
+    pub fn ip_timeout(&self) -> Option<&str> {
+        self.ip_timeout.as_deref()
+    }
+
+    pub fn allow_reserved(&self) -> &[Prefix] {
+        &self.allow_reserved
+    }
+
+    pub const fn only_v4(&self) -> bool {
+        self.only_v4
+    }
+
+    pub const fn only_v6(&self) -> bool {
+        self.only_v6
+    }
+
+    pub const fn mode(&self, family: Family) -> Option<FamilyMode> {
+        match family {
+            Family::V4 => self.ipv4_mode,
+            Family::V6 => self.ipv6_mode,
+        }
+    }
+
+    pub const fn absent(&self, family: Family) -> Option<AbsentPolicy> {
+        match family {
+            Family::V4 => self.ipv4_absent,
+            Family::V6 => self.ipv6_absent,
+        }
+    }
+
+    pub const fn hook(&self) -> Option<&Hook> {
+        match &self.command {
+            Some(Command::Hook(hook)) => Some(hook),
+            None => None,
+        }
+    }
+}
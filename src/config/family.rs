@@ -0,0 +1,35 @@
+use crate::api::cloudflare::DnsRecordType;
+use serde::Deserialize;
+use std::net::IpAddr;
+
+#[derive(Deserialize, Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Family {
+    V4,
+    V6,
+}
+
+impl Family {
+    pub const fn of(address: IpAddr) -> Self {
+        match address {
+            IpAddr::V4(_) => Self::V4,
+            IpAddr::V6(_) => Self::V6,
+        }
+    }
+
+    pub const fn record_type(self) -> DnsRecordType {
+        match self {
+            Self::V4 => DnsRecordType::A,
+            Self::V6 => DnsRecordType::AAAA,
+        }
+    }
+}
+
+impl std::fmt::Display for Family {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::V4 => write!(f, "IPv4"),
+            Self::V6 => write!(f, "IPv6"),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// How much an address family matters to an update, i.e. whether failing to discover or publish its address fails the
+/// run.
+#[derive(Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum FamilyMode {
+    /// A failure fails the run; this is the default, so that a broken family is not missed.
+    #[default]
+    Required,
+    /// A failure is reported, but the run still succeeds if the other family was updated.
+    Optional,
+    /// The family is skipped quietly when no network interface has a global address in it, and required otherwise.
+    Auto,
+}
+
+impl FromStr for FamilyMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "required" => Ok(Self::Required),
+            "optional" => Ok(Self::Optional),
+            "auto" => Ok(Self::Auto),
+            _ => Err(format!("expected required, optional or auto; got: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for FamilyMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Required => write!(f, "required"),
+            Self::Optional => write!(f, "optional"),
+            Self::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::FamilyMode;
+
+    #[test]
+    fn from_str() {
+        assert_eq!("required".parse(), Ok(FamilyMode::Required));
+        assert_eq!("optional".parse(), Ok(FamilyMode::Optional));
+        assert_eq!("auto".parse(), Ok(FamilyMode::Auto));
+        assert!("Auto".parse::<FamilyMode>().is_err());
+    }
+}
@@ -0,0 +1,1116 @@
+pub use absent_policy::AbsentPolicy;
+pub use args::Args;
+pub use family::Family;
+pub use family_mode::FamilyMode;
+pub use multiple_policy::MultiplePolicy;
+pub use record::Record;
+pub use zone::Zone;
+
+use crate::api::cloudflare::Authentication;
+use crate::api::ip::{Discovery, Endpoint, Fritzbox, Interface, Prefix, Router, Source};
+use anyhow::Context;
+use serde::Deserialize;
+use std::io::Read;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+mod absent_policy;
+mod api_token;
+mod args;
+mod family;
+mod family_mode;
+mod multiple_policy;
+mod record;
+mod zone;
+
+/// The contents of a configuration file passed via `--config`.
+#[derive(Deserialize, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    api_token: Option<String>,
+    api_token_file: Option<PathBuf>,
+    api_key: Option<String>,
+    email: Option<String>,
+    interval: Option<String>,
+    #[serde(default)]
+    watch: Vec<String>,
+    debounce: Option<String>,
+    max_age: Option<String>,
+    state_file: Option<PathBuf>,
+    ip_timeout: Option<String>,
+    ipv4: Option<Discovery>,
+    ipv6: Option<Discovery>,
+    ipv4_mode: Option<FamilyMode>,
+    ipv6_mode: Option<FamilyMode>,
+    ipv4_absent: Option<AbsentPolicy>,
+    ipv6_absent: Option<AbsentPolicy>,
+    ipv6_prefix_length: Option<u8>,
+    #[serde(default)]
+    allow_reserved: Vec<Prefix>,
+    #[serde(default)]
+    create_missing: bool,
+    #[serde(default)]
+    zones: Vec<Zone>,
+    /// Records whose zone is derived from their name.
+    #[serde(default)]
+    records: Vec<Record>,
+}
+
+impl ConfigFile {
+    fn read(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read configuration file: {}", path.display()))?;
+
+        toml::from_str(&contents).with_context(|| format!("failed to parse configuration file: {}", path.display()))
+    }
+}
+
+/// The resolved configuration; the configuration file (if any) with the command-line arguments applied over the top.
+#[allow(clippy::struct_excessive_bools)] // command-line switches
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Config {
+    authentication: Authentication,
+    create_missing: bool,
+    dry_run: bool,
+    daemon: bool,
+    interval: Duration,
+    watch: Vec<String>,
+    debounce: Duration,
+    force: bool,
+    max_age: Duration,
+    state_file: Option<PathBuf>,
+    ipv4: Discovery,
+    ipv6: Discovery,
+    ipv4_mode: FamilyMode,
+    ipv6_mode: FamilyMode,
+    ipv4_absent: AbsentPolicy,
+    ipv6_absent: AbsentPolicy,
+    ipv6_prefix_length: u8,
+    allow_reserved: Vec<Prefix>,
+    zones: Vec<Zone>,
+    fingerprint: String,
+}
+
+/// How often to check for IP address changes in daemon mode when not otherwise configured.
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// How long watched interfaces must go without an address change before checking, when not otherwise configured.
+const DEFAULT_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// How long the IP addresses can go unchanged before the DNS records are checked anyway, when not otherwise configured.
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How much of the discovered IPv6 address is the prefix that interface IDs are appended to, when not otherwise
+/// configured.
+const DEFAULT_IPV6_PREFIX_LENGTH: u8 = 64;
+
+impl Config {
+    pub fn load(args: &Args) -> anyhow::Result<Self> {
+        if args.only_v4() && args.only_v6() {
+            anyhow::bail!("--only-v4 and --only-v6 are exclusive arguments; pick one or neither");
+        }
+
+        if args.dry_run() && args.daemon() {
+            anyhow::bail!("--dry-run and --daemon are exclusive arguments; pick one or neither");
+        }
+
+        if args.hook().is_some() && args.daemon() {
+            anyhow::bail!("--daemon cannot be used with the hook subcommand; the hook runs once per address change");
+        }
+
+        let file = match args.config() {
+            Some(path) => ConfigFile::read(path)?,
+            None => ConfigFile::default(),
+        };
+
+        let authentication = Self::resolve_authentication(args, &file)?;
+
+        let interval =
+            parse_duration(args.interval(), file.interval.as_deref(), "interval")?.unwrap_or(DEFAULT_INTERVAL);
+
+        if interval.as_secs() == 0 {
+            anyhow::bail!("interval must be at least one second");
+        }
+
+        let max_age = parse_duration(args.max_age(), file.max_age.as_deref(), "max age")?.unwrap_or(DEFAULT_MAX_AGE);
+        let ip_timeout = parse_duration(args.ip_timeout(), file.ip_timeout.as_deref(), "IP timeout")?;
+        let debounce =
+            parse_duration(args.debounce(), file.debounce.as_deref(), "debounce")?.unwrap_or(DEFAULT_DEBOUNCE);
+
+        let watch = if args.watch().is_empty() { file.watch } else { args.watch().to_vec() };
+
+        if !watch.is_empty() && !args.daemon() {
+            anyhow::bail!("--watch requires --daemon to also be passed");
+        }
+
+        if !watch.is_empty() && !cfg!(target_os = "linux") {
+            anyhow::bail!("--watch is only supported on Linux");
+        }
+
+        let stdin = Self::read_stdin(args)?;
+        let ipv4 = Self::discovery(args, Family::V4, file.ipv4, ip_timeout, &stdin)?;
+        let ipv6 = Self::discovery(args, Family::V6, file.ipv6, ip_timeout, &stdin)?;
+
+        if args.fritzbox_username().is_some()
+            && ![&ipv4, &ipv6].iter().any(|discovery| discovery.source() == Source::Fritzbox)
+        {
+            anyhow::bail!("--fritzbox-username requires --ipv4-fritzbox or --ipv6-fritzbox (or a FRITZ!Box source)");
+        }
+
+        let ipv6_prefix_length =
+            args.ipv6_prefix_length().or(file.ipv6_prefix_length).unwrap_or(DEFAULT_IPV6_PREFIX_LENGTH);
+
+        if ipv6_prefix_length > 128 {
+            anyhow::bail!("IPv6 prefix length must be at most 128; got: {}", ipv6_prefix_length);
+        }
+
+        let mut zones = match (args.zone(), args.zone_id(), args.domain()) {
+            (zone, zone_id, Some(domain)) => vec![Zone::new(zone, zone_id, vec![Record::new(domain)])],
+            (None, None, None) => {
+                let mut zones = file.zones;
+                if !file.records.is_empty() {
+                    zones.push(Zone::new(None, None, file.records));
+                }
+                zones
+            }
+            (_, _, None) => anyhow::bail!("--zone and --zone-id require --domain to also be passed"),
+        };
+
+        for record in zones.iter_mut().flat_map(Zone::records_mut) {
+            Self::override_record(args, record);
+            record.check_interface_id(ipv6_prefix_length)?;
+        }
+
+        if zones.iter().all(|zone| zone.records().is_empty()) {
+            anyhow::bail!("no DNS records to update; pass --domain, or --config with at least one record");
+        }
+
+        let ipv4_mode = args.mode(Family::V4).or(file.ipv4_mode).unwrap_or_default();
+        let ipv6_mode = args.mode(Family::V6).or(file.ipv6_mode).unwrap_or_default();
+
+        let mut config = Self {
+            authentication,
+            create_missing: args.create_missing() || file.create_missing,
+            dry_run: args.dry_run(),
+            daemon: args.daemon(),
+            interval,
+            watch,
+            debounce,
+            force: args.force(),
+            max_age,
+            state_file: args.state_file().map(Path::to_path_buf).or(file.state_file),
+            ipv4,
+            ipv6,
+            ipv4_mode,
+            ipv6_mode,
+            ipv4_absent: Self::absent_policy(args, Family::V4, ipv4_mode, file.ipv4_absent)?,
+            ipv6_absent: Self::absent_policy(args, Family::V6, ipv6_mode, file.ipv6_absent)?,
+            ipv6_prefix_length,
+            allow_reserved: file.allow_reserved.into_iter().chain(args.allow_reserved().iter().copied()).collect(),
+            zones,
+            fingerprint: String::new(),
+        };
+
+        config.fingerprint = config.hash_records();
+        Ok(config)
+    }
+
+    /// Applies the command-line arguments that override every record's settings.
+    fn override_record(args: &Args, record: &mut Record) {
+        if args.only_v4() {
+            record.retain_families(&[Family::V4]);
+        } else if args.only_v6() {
+            record.retain_families(&[Family::V6]);
+        }
+
+        if let Some(ttl) = args.ttl() {
+            record.set_ttl(ttl);
+        }
+
+        if let Some(proxied) = args.proxied() {
+            record.set_proxied(proxied);
+        }
+
+        if let Some(comment) = args.comment() {
+            record.set_comment(comment);
+        }
+
+        if !args.tags().is_empty() {
+            record.set_tags(args.tags().to_vec());
+        }
+
+        if let Some(multiple) = args.multiple() {
+            record.set_multiple(multiple.clone());
+        }
+    }
+
+    /// Picks between an API token and the Global API Key; a key on the command line (or in the configuration file,
+    /// when no token is on the command line) takes the place of the API token.
+    fn resolve_authentication(args: &Args, file: &ConfigFile) -> anyhow::Result<Authentication> {
+        let token_arg = args.api_token().is_some() || args.api_token_file().is_some();
+        let token_file = file.api_token.is_some() || file.api_token_file.is_some();
+
+        if token_arg && args.api_key().is_some() {
+            anyhow::bail!("--api-token/--api-token-file and --api-key are exclusive arguments; pick one");
+        }
+
+        if token_file && file.api_key.is_some() {
+            anyhow::bail!("`api_token`/`api_token_file` and `api_key` are exclusive in the config; pick one");
+        }
+
+        let key = args.api_key().or_else(|| file.api_key.as_deref().filter(|_| !token_arg));
+
+        match (key, args.email().or(file.email.as_deref())) {
+            (Some(key), Some(email)) => Ok(Authentication::Key { email: email.to_string(), key: key.to_string() }),
+            (Some(_), None) => anyhow::bail!("the Global API Key requires --email (or `email` in the config)"),
+            (None, _) if args.email().is_some() => anyhow::bail!("--email is only used with --api-key"),
+            (None, _) => api_token::Sources {
+                arg: args.api_token(),
+                arg_file: args.api_token_file(),
+                config: file.api_token.as_deref(),
+                config_file: file.api_token_file.as_deref(),
+            }
+            .resolve(|key| std::env::var(key).ok())
+            .map(Authentication::Token),
+        }
+    }
+
+    /// Picks how to discover the address of a family. URLs on the command line replace the configured providers
+    /// entirely, each read with the matching extraction (or the only one, if there is just one); an interface on the
+    /// command line is used instead of any providers.
+    fn discovery(
+        args: &Args,
+        family: Family,
+        file: Option<Discovery>,
+        timeout: Option<Duration>,
+        stdin: &str,
+    ) -> anyhow::Result<Discovery> {
+        let flag = match family {
+            Family::V4 => "ipv4",
+            Family::V6 => "ipv6",
+        };
+
+        let default = match family {
+            Family::V4 => Discovery::default_v4,
+            Family::V6 => Discovery::default_v6,
+        };
+
+        let (urls, extracts) = (args.urls(family), args.extracts(family));
+
+        let mut discovery = match (urls, extracts) {
+            ([], []) => file.unwrap_or_else(default),
+            ([], _) => anyhow::bail!("--{}-extract requires --{}-url to also be passed", flag, flag),
+            (urls, [] | [_]) => {
+                let extract = extracts.first().cloned().unwrap_or_default();
+                Discovery::new(urls.iter().map(|url| Endpoint::new(url, extract.clone())).collect(), 1)
+            }
+            (urls, extracts) if urls.len() == extracts.len() => Discovery::new(
+                urls.iter().zip(extracts).map(|(url, extract)| Endpoint::new(url, extract.clone())).collect(),
+                1,
+            ),
+            _ => anyhow::bail!("pass --{}-extract once for every --{}-url, or once for all of them", flag, flag),
+        };
+
+        if discovery.providers().is_empty() {
+            discovery.set_providers(default().providers().to_vec());
+        }
+
+        if let Some(quorum) = args.quorum(family) {
+            discovery.set_quorum(quorum);
+        }
+
+        Self::select_source(args, family, flag, stdin, &mut discovery)?;
+
+        if let Some(interface) = discovery.interface_mut() {
+            if let Some(scope) = args.scope(family) {
+                interface.set_scope(scope);
+            }
+
+            if let Some(prefix) = args.prefix(family) {
+                interface.set_prefix(prefix);
+            }
+
+            if let Some(prefer) = args.prefer(family) {
+                interface.set_prefer(prefer);
+            }
+        } else if args.scope(family).is_some() || args.prefix(family).is_some() {
+            anyhow::bail!("--{}-scope and --{}-prefix require --{}-interface", flag, flag, flag);
+        } else if args.prefer(family).is_some() {
+            anyhow::bail!("--{}-prefer requires --{}-interface", flag, flag);
+        }
+
+        Self::configure_fritzbox(args, family, flag, &mut discovery)?;
+        Self::check_source(&discovery, family, flag)?;
+
+        if let Some(timeout) = timeout {
+            discovery.set_timeout(timeout);
+
+            for provider in discovery.providers_mut().iter_mut().filter(|provider| provider.timeout().is_none()) {
+                provider.set_timeout(timeout);
+            }
+        }
+
+        Ok(discovery)
+    }
+
+    /// Switches to the source passed on the command line, if any, filling in the defaults for sources that need them.
+    fn select_source(
+        args: &Args,
+        family: Family,
+        flag: &str,
+        stdin: &str,
+        discovery: &mut Discovery,
+    ) -> anyhow::Result<()> {
+        let sources = [
+            !args.urls(family).is_empty(),
+            args.interface(family).is_some(),
+            args.address(family).is_some(),
+            args.command(family).is_some(),
+            !args.stun(family).is_empty(),
+            !args.dns(family).is_empty(),
+            args.router(family).is_some(),
+            args.fritzbox(family).is_some(),
+        ];
+
+        if sources.iter().filter(|&&source| source).count() > 1 {
+            anyhow::bail!(
+                "pass only one {} source: a URL, interface, address, command, STUN server, DNS query, router or \
+                 FRITZ!Box",
+                flag
+            );
+        }
+
+        if !args.urls(family).is_empty() {
+            discovery.set_source(Source::Http);
+        } else if let Some(name) = args.interface(family) {
+            discovery.set_source(Source::Interface);
+            discovery.set_interface(Interface::new(name));
+        } else if let Some(address) = args.address(family) {
+            discovery.set_source(Source::Static);
+            discovery.set_address(Self::parse_address(address, family, stdin)?);
+        } else if let Some(command) = args.command(family) {
+            discovery.set_source(Source::Command);
+            discovery.set_command(command);
+        } else if !args.stun(family).is_empty() {
+            discovery.set_source(Source::Stun);
+            discovery.set_stun(args.stun(family).to_vec());
+            // as with the URLs, a quorum from the configuration file was meant for its own list
+            discovery.set_quorum(args.quorum(family).unwrap_or(1));
+        } else if !args.dns(family).is_empty() {
+            discovery.set_source(Source::Dns);
+            discovery.set_dns(args.dns(family).to_vec());
+            discovery.set_quorum(args.quorum(family).unwrap_or(1));
+        } else if let Some(protocol) = args.router(family) {
+            discovery.set_source(Source::Router);
+            discovery.set_router(Router::new(protocol, None));
+        } else if let Some(url) = args.fritzbox(family) {
+            discovery.set_source(Source::Fritzbox);
+            discovery.set_fritzbox(Fritzbox::new(url));
+        }
+
+        if let Some(gateway) = args.router_gateway(family) {
+            if discovery.source() != Source::Router {
+                anyhow::bail!("--{}-router-gateway requires --{}-router", flag, flag);
+            }
+
+            let protocol = discovery.router().map(Router::protocol).unwrap_or_default();
+            discovery.set_router(Router::new(protocol, Some(gateway.to_string())));
+        }
+
+        if discovery.source() == Source::Stun && discovery.stun().is_empty() {
+            discovery.set_stun(Discovery::default_stun());
+        }
+
+        if discovery.source() == Source::Dns && discovery.dns().is_empty() {
+            discovery.set_dns(match family {
+                Family::V4 => Discovery::default_dns_v4(),
+                Family::V6 => Discovery::default_dns_v6(),
+            });
+        }
+
+        if discovery.source() == Source::Fritzbox && discovery.fritzbox().is_none() {
+            discovery.set_fritzbox(Fritzbox::default());
+        }
+
+        Ok(())
+    }
+
+    /// Applies the FRITZ!Box options on the command line, and fills in the password from the environment if there is
+    /// a username to log in with.
+    fn configure_fritzbox(args: &Args, family: Family, flag: &str, discovery: &mut Discovery) -> anyhow::Result<()> {
+        if discovery.source() != Source::Fritzbox {
+            if args.fritzbox_prefix(family) {
+                anyhow::bail!("--{}-fritzbox-prefix requires --{}-fritzbox", flag, flag);
+            }
+
+            return Ok(());
+        }
+
+        // cannot fail; filled in along with the source
+        let fritzbox = discovery.fritzbox_mut().context("no FRITZ!Box configured")?;
+
+        if let Some(username) = args.fritzbox_username() {
+            fritzbox.set_username(username);
+        }
+
+        if args.fritzbox_prefix(family) {
+            fritzbox.set_prefix(true);
+        }
+
+        fritzbox.resolve_password(|key| std::env::var(key).ok())
+    }
+
+    /// Checks that the chosen source has what it needs.
+    fn check_source(discovery: &Discovery, family: Family, flag: &str) -> anyhow::Result<()> {
+        let candidates = match discovery.source() {
+            Source::Http => Some((discovery.providers().len(), "providers")),
+            Source::Stun => Some((discovery.stun().len(), "STUN servers")),
+            Source::Dns => Some((discovery.dns().len(), "DNS queries")),
+            Source::Interface | Source::Static | Source::Command | Source::Router | Source::Fritzbox => None,
+        };
+
+        if let Some((count, what)) = candidates {
+            if discovery.quorum() == 0 || discovery.quorum() > count {
+                anyhow::bail!(
+                    "{} quorum must be between 1 and the number of {} ({}); got: {}",
+                    flag,
+                    what,
+                    count,
+                    discovery.quorum()
+                );
+            }
+        }
+
+        match discovery.source() {
+            Source::Interface if discovery.interface().is_none() => {
+                anyhow::bail!("{} source is \"interface\", but no interface is configured", flag)
+            }
+            Source::Static if discovery.address().map(Family::of) != Some(family) => {
+                anyhow::bail!("{} source is \"static\", but no {} address is configured", flag, family)
+            }
+            Source::Command if discovery.command().is_none() => {
+                anyhow::bail!("{} source is \"command\", but no command is configured", flag)
+            }
+            Source::Router if family == Family::V6 => {
+                anyhow::bail!("{} source cannot be \"router\"; routers only report their IPv4 address", flag)
+            }
+            Source::Http
+            | Source::Interface
+            | Source::Static
+            | Source::Command
+            | Source::Stun
+            | Source::Dns
+            | Source::Router
+            | Source::Fritzbox => Ok(()),
+        }
+    }
+
+    /// Resolves what to do with a family's records while it has no address; a placeholder must be in the family.
+    /// Anything but keeping them needs the family's mode to be auto, as only that finds the family to have no address.
+    fn absent_policy(
+        args: &Args,
+        family: Family,
+        mode: FamilyMode,
+        file: Option<AbsentPolicy>,
+    ) -> anyhow::Result<AbsentPolicy> {
+        let policy = args.absent(family).or(file).unwrap_or_default();
+        let flag = match family {
+            Family::V4 => "ipv4",
+            Family::V6 => "ipv6",
+        };
+
+        if policy != AbsentPolicy::Keep && mode != FamilyMode::Auto {
+            anyhow::bail!(
+                "--{}-absent {} requires --{}-mode auto; records are only withdrawn once the host has no {} address",
+                flag,
+                policy,
+                flag,
+                family
+            );
+        }
+
+        if let Some(placeholder) = policy.placeholder().filter(|&placeholder| Family::of(placeholder) != family) {
+            anyhow::bail!("cannot park the {} records at {}; it is not an {} address", family, placeholder, family);
+        }
+
+        Ok(policy)
+    }
+
+    /// Reads stdin if an address is to be taken from it; otherwise it is left alone.
+    fn read_stdin(args: &Args) -> anyhow::Result<String> {
+        let mut stdin = String::new();
+
+        if [Family::V4, Family::V6].iter().any(|&family| args.address(family) == Some("-")) {
+            std::io::stdin().read_to_string(&mut stdin).context("failed to read addresses from stdin")?;
+        }
+
+        Ok(stdin)
+    }
+
+    /// Parses an address passed on the command line; "-" takes the first line of stdin in the right family, so that
+    /// both families can be piped in at once.
+    fn parse_address(address: &str, family: Family, stdin: &str) -> anyhow::Result<IpAddr> {
+        let address = if address == "-" {
+            stdin
+                .lines()
+                .filter_map(|line| line.trim().parse().ok())
+                .find(|&address| Family::of(address) == family)
+                .with_context(|| format!("no {} address found on stdin", family))?
+        } else {
+            address.parse().with_context(|| format!("invalid {} address: {}", family, address))?
+        };
+
+        if Family::of(address) != family {
+            anyhow::bail!("{} is not an {} address", address, family);
+        }
+
+        Ok(address)
+    }
+
+    pub const fn authentication(&self) -> &Authentication {
+        &self.authentication
+    }
+
+    pub const fn create_missing(&self) -> bool {
+        self.create_missing
+    }
+
+    pub const fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub const fn daemon(&self) -> bool {
+        self.daemon
+    }
+
+    pub const fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Network interfaces whose address changes trigger a check in daemon mode.
+    pub fn watch(&self) -> &[String] {
+        &self.watch
+    }
+
+    pub const fn debounce(&self) -> Duration {
+        self.debounce
+    }
+
+    pub const fn force(&self) -> bool {
+        self.force
+    }
+
+    pub const fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    /// The state file, if one was configured; see [`crate::cache::default_path`] for where it is kept otherwise.
+    pub fn state_file(&self) -> Option<&Path> {
+        self.state_file.as_deref()
+    }
+
+    pub const fn ipv4(&self) -> &Discovery {
+        &self.ipv4
+    }
+
+    pub const fn ipv6(&self) -> &Discovery {
+        &self.ipv6
+    }
+
+    /// Whether a failure in the address family fails the run.
+    pub const fn mode(&self, family: Family) -> FamilyMode {
+        match family {
+            Family::V4 => self.ipv4_mode,
+            Family::V6 => self.ipv6_mode,
+        }
+    }
+
+    /// What to do with the records of the address family while it has no address.
+    pub const fn absent(&self, family: Family) -> &AbsentPolicy {
+        match family {
+            Family::V4 => &self.ipv4_absent,
+            Family::V6 => &self.ipv6_absent,
+        }
+    }
+
+    pub const fn ipv6_prefix_length(&self) -> u8 {
+        self.ipv6_prefix_length
+    }
+
+    /// Networks whose addresses can be published even though they are reserved; see [`crate::api::ip::Reserved`].
+    pub fn allow_reserved(&self) -> &[Prefix] {
+        &self.allow_reserved
+    }
+
+    pub fn zones(&self) -> &[Zone] {
+        &self.zones
+    }
+
+    /// Identifies the records being updated (and how), so that state saved for one configuration is not trusted by
+    /// another; it is taken as loaded, so that [`Config::retain_families`] does not change it.
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// Hashes the settings that [`Config::fingerprint`] covers; the hash is computed by hand, as it is saved and must
+    /// not change between builds.
+    fn hash_records(&self) -> String {
+        let mut lines = vec![
+            format!("create_missing={}", self.create_missing),
+            format!("ipv6_prefix_length={}", self.ipv6_prefix_length),
+            format!("ipv4_absent={}", self.ipv4_absent),
+            format!("ipv6_absent={}", self.ipv6_absent),
+        ];
+
+        for zone in &self.zones {
+            lines.push(format!("zone name={:?} id={:?}", zone.name(), zone.id()));
+
+            for record in zone.records() {
+                lines.push(format!(
+                    "record name={:?} families={:?} ttl={:?} proxied={:?} comment={:?} tags={:?} multiple={} \
+                     interface_id={:?}",
+                    record.name(),
+                    record.families(),
+                    record.ttl(),
+                    record.proxied(),
+                    record.comment(),
+                    record.tags(),
+                    record.multiple(),
+                    record.interface_id()
+                ));
+            }
+        }
+
+        format!("{:016x}", fnv1a(lines.join("\n").as_bytes()))
+    }
+
+    /// Restricts every record to these address families, e.g. the ones a hook reported; the fingerprint is unchanged,
+    /// so that runs from the hook share their saved state with the runs that update every family.
+    pub fn retain_families(&mut self, families: &[Family]) {
+        for record in self.zones.iter_mut().flat_map(Zone::records_mut) {
+            record.retain_families(families);
+        }
+    }
+
+    /// Whether any configured record wants the given address family.
+    pub fn wants(&self, family: Family) -> bool {
+        self.zones.iter().flat_map(Zone::records).any(|record| record.families().contains(&family))
+    }
+}
+
+/// Parses a human-readable duration (e.g. "5m") from the command line, or else the configuration file.
+fn parse_duration(arg: Option<&str>, file: Option<&str>, what: &str) -> anyhow::Result<Option<Duration>> {
+    arg.or(file)
+        .map(|value| humantime::parse_duration(value).with_context(|| format!("invalid {}: {}", what, value)))
+        .transpose()
+}
+
+#[cfg(test)]
+impl Config {
+    pub fn new(zone: &str, domain: &str, api_token: &str, only_v4: bool, only_v6: bool) -> Self {
+        // the mocks only respond with documentation addresses
+        let mut args = vec!["--zone", zone, "--domain", domain, "--api-token", api_token];
+        for network in ["198.51.100.0/24", "203.0.113.0/24", "2001:db8::/32"] {
+            args.extend(["--allow-reserved", network]);
+        }
+
+        if only_v4 {
+            args.push("--only-v4");
+        }
+
+        if only_v6 {
+            args.push("--only-v6");
+        }
+
+        // cannot panic; the arguments are all valid
+        Self::load(&argh::FromArgs::from_args(&["ddns-for-cloudflare"], &args).unwrap()).unwrap()
+    }
+}
+
+/// The 64-bit FNV-1a hash of the bytes.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::cloudflare::Authentication;
+    use crate::api::ip::{
+        Discovery, DnsQuery, Endpoint, Extract, Fritzbox, Interface, Preference, QueryClass, QueryType, Router,
+        RouterProtocol, Scope, Source,
+    };
+    use crate::config::{AbsentPolicy, Args, Config, Family, FamilyMode, MultiplePolicy, Record, Zone};
+    use anyhow::Context;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::path::Path;
+    use std::time::Duration;
+
+    const CONFIG: &str = "resources/tests/config/config.toml";
+
+    fn args(args: &[&str]) -> Args {
+        // cannot panic; the tests only pass valid arguments
+        argh::FromArgs::from_args(&["ddns-for-cloudflare"], args).unwrap()
+    }
+
+    #[test]
+    fn load_file() -> anyhow::Result<()> {
+        let config = Config::load(&args(&["--config", CONFIG])).context("failed to load config")?;
+
+        assert_eq!(config.authentication(), &Authentication::Token("YQSn-xWAQiiEh9qM58wZNnyQS7FUdoqGIUAbrh7T".into()));
+        assert_eq!(config.zones().len(), 3);
+        assert_eq!(config.zones()[0].name(), Some("example.com"));
+        assert_eq!(config.zones()[1].id(), Some("023e105f4ecef8ad9ca31a8372d0c353"));
+        assert_eq!(config.zones()[2].name(), None);
+        assert_eq!(config.zones()[2].id(), None);
+        assert_eq!(config.zones()[2].records()[0].name(), "a.b.example.co.uk");
+        assert_eq!(config.zones()[0].records().len(), 2);
+        assert_eq!(config.zones()[0].records()[0].families(), [Family::V4, Family::V6]);
+        assert_eq!(config.zones()[0].records()[1].families(), [Family::V4]);
+        assert_eq!(config.zones()[0].records()[1].ttl(), Some(300));
+        assert_eq!(config.zones()[0].records()[1].proxied(), Some(true));
+        assert_eq!(config.zones()[0].records()[1].multiple(), &MultiplePolicy::Collapse);
+        assert_eq!(config.zones()[0].records()[1].comment(), Some("managed by ddns-for-cloudflare"));
+        assert_eq!(config.zones()[0].records()[1].tags(), Some(&["ddns:true".to_string()][..]));
+        assert_eq!(config.zones()[0].records()[0].comment(), None);
+        assert_eq!(
+            config.zones()[1].records()[0].multiple(),
+            &MultiplePolicy::Id("372e67954025e0ba6aaa6d586b9e0b59".into())
+        );
+        assert_eq!(config.zones()[1].records()[0].name(), "example.net");
+        assert_eq!(config.interval(), Duration::from_secs(10 * 60));
+        assert_eq!(config.max_age(), Duration::from_secs(7 * 24 * 60 * 60));
+        assert!(config.watch().is_empty());
+        assert_eq!(config.debounce(), Duration::from_secs(2));
+        assert_eq!(config.state_file(), None);
+        assert_eq!(config.ipv4().quorum(), 2);
+        assert_eq!(config.ipv4().providers()[0].extract(), &Extract::Key("ip".into()));
+        assert_eq!(config.ipv4().providers()[0].timeout(), Some(Duration::from_secs(5)));
+        assert_eq!(config.ipv4().providers()[1].extract(), &Extract::Json("/ip".into()));
+        assert_eq!(config.ipv4().providers()[1].timeout(), None);
+        assert_eq!(config.ipv6().source(), Source::Interface);
+        assert_eq!(config.ipv6().providers(), Discovery::default_v6().providers());
+        assert_eq!(config.mode(Family::V4), FamilyMode::Required);
+        assert_eq!(config.mode(Family::V6), FamilyMode::Auto);
+
+        let mut interface = Interface::new("eth0");
+        interface.set_prefix("2001:db8::/32".parse().map_err(anyhow::Error::msg)?);
+        assert_eq!(config.ipv6().interface(), Some(&interface));
+        assert!(!config.daemon());
+        assert!(config.create_missing());
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_overrides() -> anyhow::Result<()> {
+        let config = Config::load(&args(&[
+            "--config",
+            CONFIG,
+            "--api-token",
+            "override",
+            "--only-v6",
+            "--daemon",
+            "--interval",
+            "1h 30m",
+            "--max-age",
+            "12h",
+            "--force",
+            "--state-file",
+            "/tmp/state.toml",
+            "--ip-timeout",
+            "2s",
+            "--watch",
+            "ppp0",
+            "--debounce",
+            "500ms",
+            "--ipv4-quorum",
+            "1",
+            "--ipv6-url",
+            "https://example.net/ip",
+            "--ipv6-url",
+            "https://example.org/ip",
+            "--ipv6-extract",
+            "json:/ip",
+            "--ttl",
+            "60",
+            "--proxied",
+            "false",
+            "--multiple",
+            "all",
+            "--comment",
+            "home",
+            "--tag",
+            "ddns:true",
+            "--tag",
+            "site:home",
+            "--ipv4-mode",
+            "optional",
+            "--ipv6-mode",
+            "auto",
+            "--ipv6-absent",
+            "park:2001:db8::dead",
+        ]))
+        .context("failed to load config")?;
+
+        assert_eq!(config.authentication(), &Authentication::Token("override".into()));
+        assert!(config.daemon());
+        assert_eq!(config.interval(), Duration::from_secs(90 * 60));
+        assert_eq!(config.max_age(), Duration::from_secs(12 * 60 * 60));
+        assert_eq!(config.watch(), ["ppp0"]);
+        assert_eq!(config.debounce(), Duration::from_millis(500));
+        assert!(config.force());
+        assert_eq!(config.state_file(), Some(Path::new("/tmp/state.toml")));
+        assert_eq!(config.ipv4().quorum(), 1);
+        assert_eq!(config.ipv4().providers()[0].timeout(), Some(Duration::from_secs(5)));
+        assert_eq!(config.ipv4().providers()[1].timeout(), Some(Duration::from_secs(2)));
+
+        let mut provider = Endpoint::new("https://example.org/ip", Extract::Json("/ip".into()));
+        provider.set_timeout(Duration::from_secs(2));
+        assert_eq!(config.ipv6().providers().len(), 2);
+        assert_eq!(config.ipv6().providers()[1], provider);
+        assert_eq!(config.ipv6().source(), Source::Http);
+        assert!(config.create_missing());
+        assert_eq!(config.zones()[0].records()[0].families(), [Family::V6]);
+        assert!(config.zones()[0].records()[1].families().is_empty());
+        assert_eq!(config.zones()[0].records()[1].ttl(), Some(60));
+        assert_eq!(config.zones()[0].records()[1].proxied(), Some(false));
+        assert_eq!(config.zones()[0].records()[1].multiple(), &MultiplePolicy::All);
+        assert_eq!(config.zones()[0].records()[1].comment(), Some("home"));
+        assert_eq!(
+            config.zones()[0].records()[1].tags(),
+            Some(&["ddns:true".to_string(), "site:home".to_string()][..])
+        );
+        assert!(!config.wants(Family::V4));
+        assert_eq!(config.mode(Family::V4), FamilyMode::Optional);
+        assert_eq!(config.mode(Family::V6), FamilyMode::Auto);
+        assert_eq!(config.absent(Family::V4), &AbsentPolicy::Keep);
+        assert_eq!(config.absent(Family::V6), &AbsentPolicy::Park("2001:db8::dead".parse()?));
+
+        let config = Config::load(&args(&["--config", CONFIG, "--zone", "example.org", "--domain", "example.org"]))
+            .context("failed to load config")?;
+
+        assert_eq!(config.zones(), [Zone::new(Some("example.org"), None, vec![Record::new("example.org")])]);
+
+        let config = Config::load(&args(&["--config", CONFIG, "--domain", "www.example.org"]))
+            .context("failed to load config")?;
+
+        assert_eq!(config.zones(), [Zone::new(None, None, vec![Record::new("www.example.org")])]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_sources() -> anyhow::Result<()> {
+        let config = Config::load(&args(&["--config", CONFIG, "--ipv4-interface", "ppp0", "--ipv4-scope", "link"]))
+            .context("failed to load config")?;
+
+        let mut interface = Interface::new("ppp0");
+        interface.set_scope(Scope::Link);
+        assert_eq!(config.ipv4().source(), Source::Interface);
+        assert_eq!(config.ipv4().interface(), Some(&interface));
+
+        let config = Config::load(&args(&["--config", CONFIG, "--ipv6-prefer", "temporary"]))
+            .context("failed to load config")?;
+
+        let mut interface = Interface::new("eth0");
+        interface.set_prefix("2001:db8::/32".parse().map_err(anyhow::Error::msg)?);
+        interface.set_prefer(Preference::Temporary);
+        assert_eq!(config.ipv6().interface(), Some(&interface));
+
+        let config =
+            Config::load(&args(&["--config", CONFIG, "--ipv4", "203.0.113.4", "--ipv6-command", "get-wan-ip"]))
+                .context("failed to load config")?;
+
+        assert_eq!(config.ipv4().source(), Source::Static);
+        assert_eq!(config.ipv4().address(), Some(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 4))));
+        assert_eq!(config.ipv6().source(), Source::Command);
+        assert_eq!(config.ipv6().command(), Some("get-wan-ip"));
+
+        let config = Config::load(&args(&["--config", CONFIG, "--ipv4-stun", "stun.example", "--ip-timeout", "3s"]))
+            .context("failed to load config")?;
+
+        assert_eq!(config.ipv4().source(), Source::Stun);
+        assert_eq!(config.ipv4().stun(), ["stun.example"]);
+        assert_eq!(config.ipv4().timeout(), Some(Duration::from_secs(3)));
+
+        let config = Config::load(&args(&["--config", CONFIG, "--ipv6-dns", "whoami.cloudflare/txt/ch@::1"]))
+            .context("failed to load config")?;
+
+        assert_eq!(config.ipv6().source(), Source::Dns);
+
+        assert_eq!(
+            config.ipv6().dns(),
+            [DnsQuery::new("::1", "whoami.cloudflare", Some(QueryType::Txt), QueryClass::Ch)]
+        );
+
+        let config = Config::load(&args(&[
+            "--config",
+            CONFIG,
+            "--ipv4-router",
+            "nat-pmp",
+            "--ipv4-router-gateway",
+            "192.0.2.1",
+        ]))
+        .context("failed to load config")?;
+
+        assert_eq!(config.ipv4().source(), Source::Router);
+        assert_eq!(config.ipv4().router(), Some(&Router::new(RouterProtocol::NatPmp, Some("192.0.2.1".into()))));
+
+        let config = Config::load(&args(&[
+            "--config",
+            CONFIG,
+            "--ipv6-fritzbox",
+            "http://192.0.2.1:49000",
+            "--ipv6-fritzbox-prefix",
+        ]))
+        .context("failed to load config")?;
+
+        let mut fritzbox = Fritzbox::new("http://192.0.2.1:49000");
+        fritzbox.set_prefix(true);
+        assert_eq!(config.ipv6().source(), Source::Fritzbox);
+        assert_eq!(config.ipv6().fritzbox(), Some(&fritzbox));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_address() -> anyhow::Result<()> {
+        let stdin = "WAN addresses:\n203.0.113.4\n2001:db8::4\n";
+
+        assert_eq!(Config::parse_address("-", Family::V4, stdin)?, IpAddr::V4(Ipv4Addr::new(203, 0, 113, 4)));
+        assert_eq!(Config::parse_address("-", Family::V6, stdin)?, "2001:db8::4".parse::<IpAddr>()?);
+        assert_eq!(Config::parse_address("2001:db8::1", Family::V6, "")?, "2001:db8::1".parse::<IpAddr>()?);
+
+        assert!(Config::parse_address("-", Family::V6, "203.0.113.4").is_err());
+        assert!(Config::parse_address("2001:db8::1", Family::V4, "").is_err());
+        assert!(Config::parse_address("wan", Family::V4, "").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_api_key() -> anyhow::Result<()> {
+        let key = Authentication::Key { email: "user@example.com".into(), key: "key".into() };
+
+        let config = Config::load(&args(&["--config", CONFIG, "--api-key", "key", "--email", "user@example.com"]))
+            .context("failed to load config")?;
+
+        assert_eq!(config.authentication(), &key);
+
+        let config =
+            Config::load(&args(&["--domain", "example.com", "--api-key", "key", "--email", "user@example.com"]))
+                .context("failed to load config")?;
+
+        assert_eq!(config.authentication(), &key);
+
+        assert!(Config::load(&args(&["--config", CONFIG, "--api-key", "key"])).is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--email", "user@example.com"])).is_err());
+        assert!(Config::load(&args(&[
+            "--domain",
+            "example.com",
+            "--api-token",
+            "token",
+            "--api-key",
+            "key",
+            "--email",
+            "user@example.com"
+        ]))
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn fingerprint() -> anyhow::Result<()> {
+        assert_eq!(super::fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(super::fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+
+        let config = Config::load(&args(&["--config", CONFIG])).context("failed to load config")?;
+        let fingerprint = config.fingerprint();
+        assert_eq!(fingerprint.len(), 16);
+        assert_eq!(Config::load(&args(&["--config", CONFIG]))?.fingerprint(), fingerprint);
+
+        let parked = Config::load(&args(&["--config", CONFIG, "--ipv6-absent", "park:2001:db8::dead"]))?;
+        let reparked = Config::load(&args(&["--config", CONFIG, "--ipv6-absent", "park:2001:db8::beef"]))?;
+        assert_ne!(parked.fingerprint(), fingerprint);
+        assert_ne!(parked.fingerprint(), reparked.fingerprint());
+
+        let commented = Config::load(&args(&["--config", CONFIG, "--comment", "home"]))?;
+        assert_ne!(commented.fingerprint(), fingerprint);
+
+        let mut hooked = config.clone();
+        hooked.retain_families(&[Family::V4]);
+        assert_eq!(hooked.fingerprint(), fingerprint);
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_invalid() {
+        assert!(Config::load(&args(&["--zone", "example.com", "--domain", "example.com"])).is_err());
+        assert!(
+            Config::load(&args(&["--zone-id", "023e105f4ecef8ad9ca31a8372d0c353", "--api-token", "token"])).is_err()
+        );
+        assert!(Config::load(&args(&["--zone", "example.com", "--api-token", "token"])).is_err());
+        assert!(Config::load(&args(&["--api-token", "token"])).is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--only-v4", "--only-v6"])).is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--interval", "5 fortnights"])).is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--interval", "0s"])).is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--max-age", "a while"])).is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--ipv4-quorum", "3"])).is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--ipv4-quorum", "0"])).is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--ipv6-extract", "plain"])).is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--ipv4-scope", "link"])).is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--ipv4-absent", "park:2001:db8::dead"])).is_err());
+        // only an auto family is ever found to have no address, so withdrawing the records of another would do nothing
+        assert!(Config::load(&args(&["--config", CONFIG, "--ipv4-absent", "delete"])).is_err());
+        assert!(
+            Config::load(&args(&["--config", CONFIG, "--ipv6-mode", "optional", "--ipv6-absent", "delete"])).is_err()
+        );
+        assert!(Config::load(&args(&[
+            "--config",
+            CONFIG,
+            "--ipv4-interface",
+            "eth0",
+            "--ipv4-url",
+            "https://example.net/ip"
+        ]))
+        .is_err());
+        assert!(Config::load(&args(&[
+            "--config",
+            CONFIG,
+            "--ipv6-url",
+            "https://example.net/ip",
+            "--ipv6-extract",
+            "plain",
+            "--ipv6-extract",
+            "plain"
+        ]))
+        .is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--dry-run", "--daemon"])).is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--watch", "eth0"])).is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--ipv4-router-gateway", "192.0.2.1"])).is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--ipv4-router", "upnp", "--ipv4", "203.0.113.4"])).is_err());
+        assert!(
+            Config::load(&args(&["--config", CONFIG, "--ipv4-stun", "stun.example", "--ipv4-quorum", "2"])).is_err()
+        );
+        assert!(Config::load(&args(&["--config", CONFIG, "--ipv6-stun", "stun.example", "--ipv6-interface", "eth0"]))
+            .is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--daemon", "--debounce", "soon"])).is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--ipv4", "203.0.113.4", "--ipv4-command", "get-wan-ip"]))
+            .is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--ipv4", "2001:db8::4"])).is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--ipv6-fritzbox-prefix"])).is_err());
+        assert!(Config::load(&args(&["--config", CONFIG, "--fritzbox-username", "ddns"])).is_err());
+        assert!(Config::load(&args(&[
+            "--config",
+            CONFIG,
+            "--ipv4-fritzbox",
+            "http://fritz.box",
+            "--ipv4-router",
+            "auto"
+        ]))
+        .is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// What to do when a name has more than one DNS record of the same type (e.g. round-robin A records).
+#[derive(Deserialize, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum MultiplePolicy {
+    /// Refuse to update the name at all; this is the default, so that round-robin records are not changed by accident.
+    #[default]
+    Fail,
+    /// Update every record to the same address.
+    All,
+    /// Update a single record, and delete the rest.
+    Collapse,
+    /// Only update the record with this ID.
+    Id(String),
+    /// Only update the record(s) with this comment.
+    Comment(String),
+}
+
+impl FromStr for MultiplePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "fail" => Ok(Self::Fail),
+            None if s == "all" => Ok(Self::All),
+            None if s == "collapse" => Ok(Self::Collapse),
+            Some(("id", id)) if !id.is_empty() => Ok(Self::Id(id.to_string())),
+            Some(("comment", comment)) => Ok(Self::Comment(comment.to_string())),
+            _ => Err(format!("expected fail, all, collapse, id:<id> or comment:<comment>; got: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for MultiplePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fail => write!(f, "fail"),
+            Self::All => write!(f, "all"),
+            Self::Collapse => write!(f, "collapse"),
+            Self::Id(id) => write!(f, "id:{}", id),
+            Self::Comment(comment) => write!(f, "comment:{}", comment),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::MultiplePolicy;
+
+    #[test]
+    fn from_str() {
+        assert_eq!("all".parse(), Ok(MultiplePolicy::All));
+        assert_eq!("collapse".parse(), Ok(MultiplePolicy::Collapse));
+        assert_eq!(
+            "id:372e67954025e0ba6aaa6d586b9e0b59".parse(),
+            Ok(MultiplePolicy::Id("372e67954025e0ba6aaa6d586b9e0b59".into()))
+        );
+        assert_eq!("comment:primary".parse(), Ok(MultiplePolicy::Comment("primary".into())));
+        assert!("id:".parse::<MultiplePolicy>().is_err());
+        assert!("some".parse::<MultiplePolicy>().is_err());
+    }
+}
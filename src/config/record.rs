@@ -0,0 +1,153 @@
+use crate::config::family::Family;
+use crate::config::multiple_policy::MultiplePolicy;
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv6Addr};
+
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct Record {
+    name: String,
+    #[serde(default = "Record::default_families")]
+    families: Vec<Family>,
+    ttl: Option<u32>,
+    proxied: Option<bool>,
+    comment: Option<String>,
+    tags: Option<Vec<String>>,
+    #[serde(default)]
+    multiple: MultiplePolicy,
+    /// The interface identifier of a host behind the discovered IPv6 prefix (e.g. `::10`); its AAAA record then gets
+    /// the prefix followed by this, instead of the discovered address itself.
+    interface_id: Option<Ipv6Addr>,
+}
+
+impl Record {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            families: Self::default_families(),
+            ttl: None,
+            proxied: None,
+            comment: None,
+            tags: None,
+            multiple: MultiplePolicy::default(),
+            interface_id: None,
+        }
+    }
+
+    fn default_families() -> Vec<Family> {
+        vec![Family::V4, Family::V6]
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn families(&self) -> &[Family] {
+        &self.families
+    }
+
+    pub const fn ttl(&self) -> Option<u32> {
+        self.ttl
+    }
+
+    pub const fn proxied(&self) -> Option<bool> {
+        self.proxied
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    pub fn tags(&self) -> Option<&[String]> {
+        self.tags.as_deref()
+    }
+
+    pub const fn multiple(&self) -> &MultiplePolicy {
+        &self.multiple
+    }
+
+    pub const fn interface_id(&self) -> Option<Ipv6Addr> {
+        self.interface_id
+    }
+
+    pub fn set_multiple(&mut self, multiple: MultiplePolicy) {
+        self.multiple = multiple;
+    }
+
+    pub const fn set_ttl(&mut self, ttl: u32) {
+        self.ttl = Some(ttl);
+    }
+
+    pub const fn set_proxied(&mut self, proxied: bool) {
+        self.proxied = Some(proxied);
+    }
+
+    pub fn set_comment(&mut self, comment: &str) {
+        self.comment = Some(comment.to_string());
+    }
+
+    pub fn set_tags(&mut self, tags: Vec<String>) {
+        self.tags = Some(tags);
+    }
+
+    pub fn retain_families(&mut self, families: &[Family]) {
+        self.families.retain(|family| families.contains(family));
+    }
+
+    /// Errors if the interface identifier has bits set inside the first `prefix_length` bits, where they would be
+    /// overwritten by the prefix.
+    pub fn check_interface_id(&self, prefix_length: u8) -> anyhow::Result<()> {
+        match self.interface_id {
+            Some(interface_id) if u128::from(interface_id) & !host_mask(prefix_length) != 0 => anyhow::bail!(
+                "interface ID {} of {} does not fit in the last {} bits of an IPv6 address",
+                interface_id,
+                self.name,
+                128 - u32::from(prefix_length)
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// The address to publish for this record; for an AAAA record with an interface identifier, this is the first
+    /// `prefix_length` bits of the discovered address followed by the identifier.
+    pub fn address(&self, discovered: IpAddr, prefix_length: u8) -> IpAddr {
+        match (discovered, self.interface_id) {
+            (IpAddr::V6(discovered), Some(interface_id)) => {
+                let mask = host_mask(prefix_length);
+                IpAddr::V6(Ipv6Addr::from(u128::from(discovered) & !mask | u128::from(interface_id) & mask))
+            }
+            _ => discovered,
+        }
+    }
+}
+
+/// The bits of an IPv6 address that come after a prefix of the given length.
+fn host_mask(prefix_length: u8) -> u128 {
+    u128::MAX.checked_shr(u32::from(prefix_length)).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Record;
+    use std::net::IpAddr;
+
+    fn ip(s: &str) -> IpAddr {
+        // cannot panic; the tests only pass valid addresses
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn address() -> anyhow::Result<()> {
+        let record: Record = toml::from_str("name = \"nas.example.com\"\ninterface_id = \"::a:b:c:d\"")?;
+
+        assert_eq!(record.address(ip("2001:db8:aa:bb::4"), 64), ip("2001:db8:aa:bb:a:b:c:d"));
+        assert_eq!(record.address(ip("2001:db8:aa:bb::4"), 56), ip("2001:db8:aa:0:a:b:c:d"));
+        assert_eq!(record.address(ip("198.51.100.4"), 64), ip("198.51.100.4"));
+        assert_eq!(Record::new("nas.example.com").address(ip("2001:db8::4"), 64), ip("2001:db8::4"));
+
+        assert!(record.check_interface_id(64).is_ok());
+        assert!(record.check_interface_id(80).is_err());
+
+        Ok(())
+    }
+}
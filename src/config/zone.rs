@@ -0,0 +1,35 @@
+use crate::config::record::Record;
+use serde::Deserialize;
+
+/// A group of records in the same DNS zone; the zone is identified by its ID, its name, or (if neither is given)
+/// derived separately for each record from the record's name.
+#[derive(Deserialize, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct Zone {
+    name: Option<String>,
+    id: Option<String>,
+    #[serde(default)]
+    records: Vec<Record>,
+}
+
+impl Zone {
+    pub fn new(name: Option<&str>, id: Option<&str>, records: Vec<Record>) -> Self {
+        Self { name: name.map(str::to_string), id: id.map(str::to_string), records }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    pub const fn records_mut(&mut self) -> &mut Vec<Record> {
+        &mut self.records
+    }
+}
@@ -0,0 +1,152 @@
+use crate::api::ip::InterfaceAddress;
+use crate::api::{cloudflare, ip};
+use crate::cache::Cache;
+use crate::config::Config;
+use anyhow::Context;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The longest the daemon will wait between checks while backing off from repeated failures, unless the configured
+/// interval is itself longer.
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// What woke the daemon before its next scheduled check.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Event {
+    Shutdown,
+    AddressChanged,
+}
+
+/// Repeatedly updates the configured records until SIGINT or SIGTERM (or Ctrl+C on Windows) is received; also checks
+/// straight away whenever a watched interface's addresses change.
+// mocked
+#[cfg(not(tarpaulin_include))]
+pub fn run(config: &Config, cloudflare: &cloudflare::Client, ip: &ip::Client, mut cache: Cache) -> anyhow::Result<()> {
+    let (sender, events) = mpsc::channel();
+
+    let shutdown = sender.clone();
+    ctrlc::set_handler(move || {
+        // the receiver only goes away once the loop below has already exited
+        let _ = shutdown.send(Event::Shutdown);
+    })
+    .context("failed to register signal handler")?;
+
+    if !config.watch().is_empty() {
+        let interfaces = config.watch().to_vec();
+
+        thread::spawn(move || {
+            // stops once the receiver goes away
+            if let Err(e) = InterfaceAddress::watch(&interfaces, || sender.send(Event::AddressChanged).is_ok()) {
+                eprintln!("Stopped watching network interfaces, falling back to the interval: {:#}", e);
+            }
+        });
+    }
+
+    let mut failures = 0;
+
+    loop {
+        match crate::update(config, cloudflare, ip, &mut cache) {
+            Ok(()) => failures = 0,
+            Err(e) => {
+                failures += 1;
+                eprintln!("Update failed ({} in a row): {:#}", failures, e);
+            }
+        }
+
+        let delay = delay(config.interval(), failures, fastrand::f64());
+        println!("Next check in {}", humantime::format_duration(Duration::from_secs(delay.as_secs())));
+
+        match wait(&events, delay, config.debounce()) {
+            None => {}
+            Some(Event::AddressChanged) => println!("Address change detected; checking now"),
+            Some(Event::Shutdown) => {
+                println!("Shutting down...");
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Waits up to `delay` for an event, returning `None` if there was none. After an address change, keeps waiting until
+/// `debounce` passes without another, so that a burst of changes (e.g. an interface coming up) only causes one check;
+/// a shutdown is always returned straight away.
+fn wait(events: &Receiver<Event>, delay: Duration, debounce: Duration) -> Option<Event> {
+    match events.recv_timeout(delay) {
+        Err(RecvTimeoutError::Timeout) => None,
+        Ok(Event::Shutdown) | Err(RecvTimeoutError::Disconnected) => Some(Event::Shutdown),
+        Ok(Event::AddressChanged) => {
+            let mut quiet = Instant::now() + debounce;
+
+            loop {
+                match events.recv_timeout(quiet.saturating_duration_since(Instant::now())) {
+                    Err(RecvTimeoutError::Timeout) => return Some(Event::AddressChanged),
+                    Ok(Event::AddressChanged) => quiet = Instant::now() + debounce,
+                    Ok(Event::Shutdown) | Err(RecvTimeoutError::Disconnected) => return Some(Event::Shutdown),
+                }
+            }
+        }
+    }
+}
+
+/// How long to wait before the next check: the interval, doubled for each consecutive failure (up to
+/// [`MAX_BACKOFF`]), then spread by up to ±10% using `jitter` (in `0.0..1.0`) so that many hosts started together do
+/// not all hit the API at the same moment.
+pub fn delay(interval: Duration, failures: u32, jitter: f64) -> Duration {
+    let backoff = interval.saturating_mul(2_u32.saturating_pow(failures)).min(MAX_BACKOFF.max(interval));
+
+    backoff.mul_f64(jitter.mul_add(0.2, 0.9))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::daemon::{delay, wait, Event};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    const INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+    #[test]
+    fn delay_jitter() {
+        assert_eq!(delay(INTERVAL, 0, 0.0), Duration::from_secs(270));
+        assert_eq!(delay(INTERVAL, 0, 0.5), INTERVAL);
+        assert_eq!(delay(INTERVAL, 0, 1.0), Duration::from_secs(330));
+    }
+
+    #[test]
+    fn delay_backoff() {
+        assert_eq!(delay(INTERVAL, 1, 0.5), Duration::from_secs(10 * 60));
+        assert_eq!(delay(INTERVAL, 2, 0.5), Duration::from_secs(20 * 60));
+        assert_eq!(delay(INTERVAL, 4, 0.5), Duration::from_secs(60 * 60));
+        assert_eq!(delay(INTERVAL, u32::MAX, 0.5), Duration::from_secs(60 * 60));
+        assert_eq!(delay(Duration::from_secs(2 * 60 * 60), 3, 0.5), Duration::from_secs(2 * 60 * 60));
+    }
+
+    #[test]
+    fn wait_debounce() {
+        let (sender, events) = mpsc::channel();
+        let debounce = Duration::from_millis(50);
+
+        assert_eq!(wait(&events, Duration::from_millis(10), debounce), None);
+
+        let burst = sender.clone();
+        let start = Instant::now();
+        thread::spawn(move || {
+            for _ in 0..3 {
+                burst.send(Event::AddressChanged).unwrap();
+                thread::sleep(Duration::from_millis(20));
+            }
+        });
+        assert_eq!(wait(&events, INTERVAL, debounce), Some(Event::AddressChanged));
+        assert!(start.elapsed() >= Duration::from_millis(90));
+        assert!(events.try_recv().is_err());
+
+        sender.send(Event::AddressChanged).unwrap();
+        sender.send(Event::Shutdown).unwrap();
+        assert_eq!(wait(&events, INTERVAL, debounce), Some(Event::Shutdown));
+
+        drop(sender);
+        assert_eq!(wait(&events, INTERVAL, debounce), Some(Event::Shutdown));
+    }
+}
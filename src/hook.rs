@@ -0,0 +1,144 @@
+use crate::api::ip::Scope;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+/// `reason`s that dhclient and dhcpcd pass with a newly acquired (or confirmed) IPv4 address.
+const DHCP_REASONS_V4: [&str; 6] = ["BOUND", "RENEW", "REBIND", "REBOOT", "TIMEOUT", "STATIC"];
+
+/// `reason`s that dhclient and dhcpcd pass with a newly acquired (or confirmed) IPv6 address.
+const DHCP_REASONS_V6: [&str; 5] = ["BOUND6", "RENEW6", "REBIND6", "REBOOT6", "INFORM6"];
+
+/// The addresses reported by the hook that ran this utility; either family is `None` if the hook did not report one
+/// (e.g. dhclient's `EXPIRE`, or pppd, which only reports IPv4).
+///
+/// Recognises, in order:
+/// - dhclient and dhcpcd, from `reason` and `new_ip_address` (or `new_ip6_address` or `new_dhcp6_ia_na1_ia_addr1`)
+/// - networkd-dispatcher, from `IFACE` and `ADDR` (or `IP_ADDRS`), and `IP6_ADDRS`
+/// - pppd's `ip-up`, from `PPP_LOCAL` or its fourth argument
+pub fn addresses(
+    args: &[String],
+    env: fn(&str) -> Option<String>,
+) -> anyhow::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)> {
+    if let Some(reason) = env("reason") {
+        let ipv4 =
+            if DHCP_REASONS_V4.contains(&reason.as_str()) { parse(env("new_ip_address").as_deref())? } else { None };
+        let ipv6 = if DHCP_REASONS_V6.contains(&reason.as_str()) {
+            parse(env("new_ip6_address").or_else(|| env("new_dhcp6_ia_na1_ia_addr1")).as_deref())?
+        } else {
+            None
+        };
+
+        return Ok((ipv4, ipv6));
+    }
+
+    if env("IFACE").is_some() {
+        let ipv4 = first_global(env("ADDR").or_else(|| env("IP_ADDRS")))?;
+        let ipv6 = first_global(env("IP6_ADDRS"))?;
+
+        return Ok((ipv4, ipv6));
+    }
+
+    if let Some(local) = env("PPP_LOCAL").or_else(|| args.get(3).cloned()) {
+        return Ok((parse(Some(&local))?, None));
+    }
+
+    anyhow::bail!(
+        "no address found in the hook's environment; expected the variables set by dhclient, dhcpcd, \
+         networkd-dispatcher or pppd"
+    )
+}
+
+/// Parses an address, treating an empty value the same as a missing one.
+fn parse<T: FromStr>(value: Option<&str>) -> anyhow::Result<Option<T>> {
+    match value.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) => {
+            T::from_str(value).map(Some).map_err(|_| anyhow::anyhow!("the hook passed an invalid address: {}", value))
+        }
+    }
+}
+
+/// The first global-scope address in a space-separated list.
+fn first_global<T: FromStr + Into<IpAddr> + Copy>(value: Option<String>) -> anyhow::Result<Option<T>> {
+    for address in value.unwrap_or_default().split_whitespace() {
+        let address: T =
+            T::from_str(address).map_err(|_| anyhow::anyhow!("the hook passed an invalid address: {}", address))?;
+
+        if Scope::of(address.into()) == Scope::Global {
+            return Ok(Some(address));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hook;
+    use anyhow::Context;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    fn dhclient(key: &str) -> Option<String> {
+        match key {
+            "reason" => Some("BOUND".into()),
+            "new_ip_address" => Some("198.51.100.4".into()),
+            "interface" => Some("eth0".into()),
+            _ => None,
+        }
+    }
+
+    fn dhclient_v6(key: &str) -> Option<String> {
+        match key {
+            "reason" => Some("RENEW6".into()),
+            "new_ip6_address" => Some("2001:db8::4".into()),
+            _ => None,
+        }
+    }
+
+    fn dhclient_expire(key: &str) -> Option<String> {
+        match key {
+            "reason" => Some("EXPIRE".into()),
+            "old_ip_address" => Some("198.51.100.4".into()),
+            _ => None,
+        }
+    }
+
+    fn networkd_dispatcher(key: &str) -> Option<String> {
+        match key {
+            "IFACE" => Some("eth0".into()),
+            "STATE" => Some("routable".into()),
+            "IP_ADDRS" => Some("198.51.100.4".into()),
+            "IP6_ADDRS" => Some("fe80::1 2001:db8::4".into()),
+            _ => None,
+        }
+    }
+
+    fn empty(_: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn addresses() -> anyhow::Result<()> {
+        let ipv4 = Ipv4Addr::new(198, 51, 100, 4);
+        let ipv6 = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 4);
+
+        assert_eq!(hook::addresses(&[], dhclient).context("failed to read dhclient")?, (Some(ipv4), None));
+        assert_eq!(hook::addresses(&[], dhclient_v6).context("failed to read dhclient")?, (None, Some(ipv6)));
+        assert_eq!(hook::addresses(&[], dhclient_expire).context("failed to read dhclient")?, (None, None));
+        assert_eq!(
+            hook::addresses(&[], networkd_dispatcher).context("failed to read networkd-dispatcher")?,
+            (Some(ipv4), Some(ipv6))
+        );
+
+        let pppd: Vec<_> = ["ppp0", "/dev/ttyS0", "115200", "198.51.100.4", "203.0.113.1", ""]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(hook::addresses(&pppd, empty).context("failed to read pppd")?, (Some(ipv4), None));
+
+        assert!(hook::addresses(&[], empty).is_err());
+        assert!(hook::addresses(&pppd[..3], empty).is_err());
+
+        Ok(())
+    }
+}
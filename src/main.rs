@@ -1,8 +1,11 @@
 // Enable all clippy lints and enforce, and opt out of individual lints
-#![cfg_attr(feature = "cargo-clippy", warn(clippy::cargo, clippy::pedantic, clippy::nursery))]
-#![cfg_attr(
-    feature = "cargo-clippy",
-    allow(clippy::default_trait_access, clippy::module_name_repetitions, clippy::must_use_candidate)
+#![warn(clippy::cargo, clippy::pedantic, clippy::nursery)]
+#![allow(
+    clippy::default_trait_access,
+    clippy::module_name_repetitions,
+    clippy::must_use_candidate,
+    clippy::result_large_err,
+    clippy::uninlined_format_args
 )]
 //
 // Force certain lints to be errors
@@ -10,111 +13,692 @@
 
 use anyhow::Context;
 use api::cloudflare;
-use api::cloudflare::DnsRecordType;
+use api::cloudflare::{DnsRecord, DnsRecordPatch, DnsRecordType};
 use api::ip;
-use config::Config;
-use std::net::IpAddr;
+use api::ip::Reserved;
+use cache::Cache;
+use config::{AbsentPolicy, Args, Config, Family, FamilyMode, MultiplePolicy, Record};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use summary::{Outcome, Summary};
 
 #[doc(hidden)]
 mod api;
 
+#[doc(hidden)]
+mod cache;
+
 #[doc(hidden)]
 mod config;
 
+#[doc(hidden)]
+mod daemon;
+
+#[doc(hidden)]
+mod hook;
+
+#[doc(hidden)]
+mod summary;
+
 // mocked
 #[doc(hidden)]
 #[cfg(not(tarpaulin_include))]
 fn main() -> anyhow::Result<()> {
-    let config: Config = argh::from_env();
+    let args: Args = argh::from_env();
+    let mut config = Config::load(&args)?;
+
+    let hook = match args.hook() {
+        Some(hook) => Some(hook::addresses(hook.args(), |key| std::env::var(key).ok())?),
+        None => None,
+    };
+
+    if let Some((ipv4, ipv6)) = hook {
+        let families: Vec<_> = ipv4.map(|_| Family::V4).into_iter().chain(ipv6.map(|_| Family::V6)).collect();
+        config.retain_families(&families);
+
+        if families.is_empty() || !(config.wants(Family::V4) || config.wants(Family::V6)) {
+            println!("The hook did not report an address for any configured record; nothing to do");
+            return Ok(());
+        }
+    }
+
+    let cloudflare = cloudflare::Client::new(config.authentication());
+    let ip = ip::Client::new(config.ipv4(), config.ipv6());
 
-    if config.only_v4() && config.only_v6() {
-        anyhow::bail!("--only-v4 and --only-v6 are exclusive arguments; pick one or neither");
+    let mut cache = config
+        .state_file()
+        .map(Path::to_path_buf)
+        .or_else(|| cache::default_path(|key| std::env::var(key).ok()))
+        .map_or_else(Cache::default, Cache::load);
+
+    if config.force() {
+        cache.clear();
+    }
+
+    if let Some((ipv4, ipv6)) = hook {
+        publish(&config, &cloudflare, &mut cache, ipv4, ipv6, Summary::default())
+    } else if config.daemon() {
+        daemon::run(&config, &cloudflare, &ip, cache)
+    } else {
+        update(&config, &cloudflare, &ip, &mut cache)
     }
+}
 
-    let cloudflare = cloudflare::Client::new(config.api_token());
-    let ip = ip::Client::new();
+#[doc(hidden)]
+fn update(config: &Config, cloudflare: &cloudflare::Client, ip: &ip::Client, cache: &mut Cache) -> anyhow::Result<()> {
+    let mut summary = Summary::default();
+    let ipv4 = discover(config, &mut summary, Family::V4, || ip.has_global_v4(), || ip.v4());
+    let ipv6 = discover(config, &mut summary, Family::V6, || ip.has_global_v6(), || ip.v6());
 
-    update(&config, cloudflare, ip)
+    publish(config, cloudflare, cache, ipv4, ipv6, summary)
 }
 
+/// Discovers the address of a family the records want; a failure is recorded rather than returned, so that the other
+/// family is still updated.
 #[doc(hidden)]
-fn update(config: &Config, cloudflare: cloudflare::Client, ip: ip::Client) -> anyhow::Result<()> {
-    let zone = cloudflare.fetch_zone(config.zone()).context("failed to fetch DNS Zone")?;
+fn discover<T>(
+    config: &Config,
+    summary: &mut Summary,
+    family: Family,
+    has_global: impl FnOnce() -> bool,
+    discover: impl FnOnce() -> anyhow::Result<T>,
+) -> Option<T> {
+    if !config.wants(family) {
+        return None;
+    }
 
-    let ipv4 = if config.only_v6() {
-        None
-    } else {
-        let record = cloudflare
-            .fetch_dns_record(zone.id(), config.domain(), DnsRecordType::A)
-            .context("failed to fetch DNS A Record")?;
-        let ip = ip.v4().context("failed to fetch IPv4 address")?;
+    if config.mode(family) == FamilyMode::Auto && !has_global() {
+        summary.set(family, Outcome::Skipped);
+        return None;
+    }
 
-        if record.content() == ip {
-            println!("A Record already matches desired IPv4; skipping...");
-            None
-        } else if record.locked() {
-            println!("A Record is locked; skipping...");
+    match discover() {
+        Ok(ip) => Some(ip),
+        Err(e) => {
+            summary.set(family, Outcome::Failed(format!("{:#}", e)));
             None
-        } else {
-            Some((record, ip))
         }
+    }
+}
+
+/// Points every configured record at the given addresses. Records in a family whose address could not be discovered
+/// or published (as recorded in `summary`) are left alone; those in a family the host has no address in at all (i.e.
+/// one that was skipped) are withdrawn as the family's [`AbsentPolicy`] asks.
+#[doc(hidden)]
+fn publish(
+    config: &Config,
+    cloudflare: &cloudflare::Client,
+    cache: &mut Cache,
+    ipv4: Option<Ipv4Addr>,
+    ipv6: Option<Ipv6Addr>,
+    mut summary: Summary,
+) -> anyhow::Result<()> {
+    let mut allowed = |ip| match check_address(config, ip) {
+        Ok(()) => true,
+        Err(e) => {
+            summary.set(Family::of(ip), Outcome::Failed(format!("{:#}", e)));
+            false
+        }
+    };
+    let ipv4 = ipv4.filter(|&ip| allowed(IpAddr::V4(ip)));
+    let ipv6 = ipv6.filter(|&ip| allowed(IpAddr::V6(ip)));
+    let address = |family| match family {
+        Family::V4 => ipv4.map(IpAddr::V4),
+        Family::V6 => ipv6.map(IpAddr::V6),
+    };
+
+    // a failure says nothing about whether the family has an address, so only a skipped family is withdrawn
+    let absent_v4 = summary.outcome(Family::V4) == Some(&Outcome::Skipped);
+    let absent_v6 = summary.outcome(Family::V6) == Some(&Outcome::Skipped);
+    let absent = |family| match family {
+        Family::V4 => absent_v4,
+        Family::V6 => absent_v6,
     };
 
-    let ipv6 = if config.only_v4() {
-        None
+    // records in a family without an address may still have to be deleted or parked
+    let withdrawing = config.zones().iter().flat_map(config::Zone::records).any(|record| {
+        record.families().iter().any(|&family| absent(family) && !is_withdrawn(config, cache, record, family))
+    });
+
+    if ipv4.is_none() && ipv6.is_none() && !withdrawing {
+        return summary.finish(|family| config.mode(family));
+    }
+
+    if config.dry_run() {
+        println!("Dry run; no DNS Records will be changed");
+    }
+
+    let fingerprint = config.fingerprint();
+    let now = SystemTime::now();
+
+    if cache.is_stale(fingerprint, config.max_age(), now) {
+        cache.clear();
+    } else if cache.is_published(ipv4, ipv6) && !withdrawing && !config.dry_run() {
+        println!("IP address(es) unchanged since the last check; skipping (pass --force to check anyway)");
+
+        for family in [Family::V4, Family::V6] {
+            if let Some(ip) = address(family) {
+                summary.set(family, Outcome::Unchanged(ip));
+            }
+        }
+
+        return summary.finish(|family| config.mode(family));
+    }
+
+    for zone in config.zones() {
+        for record in zone.records() {
+            let zone_id = match zone_id(cloudflare, cache, zone, record.name()) {
+                Ok(zone_id) => zone_id,
+                Err(e) => {
+                    eprintln!("{}: {:#}", record.name(), e);
+
+                    for &family in record.families() {
+                        if let Some(ip) = address(family) {
+                            summary.fail_record(family, ip);
+                        } else if absent(family) && !is_withdrawn(config, cache, record, family) {
+                            summary.fail_withdrawal(family);
+                        }
+                    }
+
+                    continue;
+                }
+            };
+
+            for &family in record.families() {
+                let Some(ip) = address(family) else {
+                    if absent(family) {
+                        if let Err(e) = withdraw_record(config, cloudflare, cache, &zone_id, record, family) {
+                            eprintln!("{}: {:#}", record.name(), e);
+                            summary.fail_withdrawal(family);
+                        }
+                    }
+
+                    continue;
+                };
+
+                let content = record.address(ip, config.ipv6_prefix_length());
+
+                match update_record(config, cloudflare, cache, &zone_id, record, family, content) {
+                    Ok(()) if config.dry_run() => {}
+                    Ok(()) => restore_record(cache, record, family),
+                    Err(e) => {
+                        eprintln!("{}: {:#}", record.name(), e);
+                        summary.fail_record(family, ip);
+                    }
+                }
+            }
+        }
+    }
+
+    if !config.dry_run() {
+        for family in [Family::V4, Family::V6] {
+            if let Some(ip) = address(family).filter(|_| !summary.is_failed(family)) {
+                summary.set(family, Outcome::Published(ip));
+            }
+        }
+
+        let ipv4 = ipv4.filter(|_| !summary.is_failed(Family::V4));
+        let ipv6 = ipv6.filter(|_| !summary.is_failed(Family::V6));
+
+        // a family that failed is left as it was, so that it is tried again next time
+        if ipv4.is_some() || ipv6.is_some() {
+            cache.set_published(fingerprint, ipv4, ipv6, now);
+        }
+
+        // the records have been updated either way; the next run will just make a few more API calls
+        if let Err(e) = cache.save() {
+            eprintln!("Failed to save state: {:#}", e);
+        }
+    }
+
+    summary.finish(|family| config.mode(family))
+}
+
+/// Refuses to publish an address that cannot be reached from the internet, unless it was explicitly allowed.
+#[doc(hidden)]
+fn check_address(config: &Config, ip: IpAddr) -> anyhow::Result<()> {
+    let Some(reserved) = Reserved::of(ip) else {
+        return Ok(());
+    };
+
+    if reserved == Reserved::CarrierGradeNat {
+        eprintln!(
+            "Warning: {} is a carrier-grade NAT address; the ISP shares a public IPv4 address between customers, so \
+             inbound connections will not reach this host",
+            ip
+        );
+    }
+
+    if !config.allow_reserved().iter().any(|prefix| prefix.contains(ip)) {
+        anyhow::bail!(
+            "refusing to publish {} address {}; pass --allow-reserved with a network containing it to publish it \
+             anyway",
+            reserved,
+            ip
+        );
+    }
+
+    Ok(())
+}
+
+/// Resolves the ID of the zone a record is in; from the configured ID, by looking up the configured name, or by
+/// finding the zone the record's name belongs to.
+#[doc(hidden)]
+fn zone_id(
+    cloudflare: &cloudflare::Client,
+    cache: &mut Cache,
+    zone: &config::Zone,
+    domain: &str,
+) -> anyhow::Result<String> {
+    if let Some(zone_id) = zone.id() {
+        return Ok(zone_id.to_string());
+    }
+
+    if let Some(name) = zone.name() {
+        if let Some(zone_id) = cache.zone_id(name) {
+            return Ok(zone_id.to_string());
+        }
+
+        let zone_id = cloudflare.fetch_zone(name).context("failed to fetch DNS Zone")?.id().to_string();
+        cache.set_zone_id(name, &zone_id);
+
+        return Ok(zone_id);
+    }
+
+    if !cache.zones_listed() {
+        let zones = cloudflare.fetch_zones().context("failed to fetch DNS Zones")?;
+        cache.set_zones(&zones);
+    }
+
+    cache
+        .zone_id_for(domain)
+        .map(str::to_string)
+        .with_context(|| format!("none of the DNS Zones the API token can access contain {}", domain))
+}
+
+#[doc(hidden)]
+fn update_record(
+    config: &Config,
+    cloudflare: &cloudflare::Client,
+    cache: &mut Cache,
+    zone_id: &str,
+    record: &Record,
+    family: Family,
+    ip: IpAddr,
+) -> anyhow::Result<()> {
+    let record_type = family.record_type();
+
+    let mut dns_records = match cache.dns_records(zone_id, record.name(), record_type) {
+        Some(dns_records) => dns_records.to_vec(),
+        None => cloudflare
+            .fetch_dns_records(zone_id, record.name(), record_type)
+            .with_context(|| format!("failed to fetch DNS {} Records", record_type))?,
+    };
+
+    if dns_records.is_empty() {
+        // a record deleted because its family had no address is created again without being asked to
+        if !config.create_missing() && cache.withdrawal(record.name(), record_type).is_none() {
+            anyhow::bail!("{} Record does not exist; create it first, or pass --create-missing", record_type);
+        }
+
+        if config.dry_run() {
+            println!("{}: {} Record does not exist; would be created with {}", record.name(), record_type, ip);
+            return Ok(());
+        }
+
+        let patch = DnsRecordPatch {
+            content: Some(ip),
+            ttl: Some(record.ttl().unwrap_or(cloudflare::TTL_AUTOMATIC)),
+            proxied: Some(record.proxied().unwrap_or(false)),
+            comment: record.comment().map(str::to_string),
+            tags: record.tags().map(<[String]>::to_vec),
+        };
+
+        let dns_record = cloudflare
+            .create_dns_record(zone_id, record.name(), record_type, &patch)
+            .with_context(|| format!("failed to create DNS {} Record", record_type))?;
+
+        println!("{}: {} Record created with: {}", record.name(), record_type, ip);
+        cache.set_dns_records(zone_id, record.name(), record_type, vec![dns_record]);
+
+        return Ok(());
+    }
+
+    let (selected, extra) = select_dns_records(record.multiple(), &dns_records, ip)
+        .with_context(|| format!("cannot choose which DNS {} Record to update", record_type))?;
+
+    let mut updated = dns_records.clone();
+    let mut failures = 0;
+
+    for index in selected {
+        let label = label(&dns_records, index, record_type);
+
+        match update_dns_record(config, cloudflare, zone_id, record, family, &mut updated[index], ip) {
+            Ok(outcome) => println!("{}: {} {}", record.name(), label, outcome),
+            Err(e) => {
+                eprintln!("{}: failed to update DNS {}: {:#}", record.name(), label, e);
+                failures += 1;
+            }
+        }
+    }
+
+    let (deleted, delete_failures) =
+        delete_dns_records(config, cloudflare, zone_id, record, record_type, &dns_records, &extra);
+    failures += delete_failures;
+
+    if failures > 0 {
+        // some records may have been changed or removed since they were cached; look them up again next time
+        cache.remove_dns_records(zone_id, record.name(), record_type);
+
+        anyhow::bail!("failed to update {} of the DNS {} Records", failures, record_type);
+    }
+
+    if !config.dry_run() {
+        dns_records = updated;
+    }
+
+    dns_records.retain(|dns_record| !deleted.iter().any(|id| id == dns_record.id()));
+    cache.set_dns_records(zone_id, record.name(), record_type, dns_records);
+
+    Ok(())
+}
+
+/// Takes a record out of service while its address family has no address, as the family's [`AbsentPolicy`] asks:
+/// deleting it, or pointing it at a placeholder. Does nothing if it was already withdrawn that way.
+#[doc(hidden)]
+fn withdraw_record(
+    config: &Config,
+    cloudflare: &cloudflare::Client,
+    cache: &mut Cache,
+    zone_id: &str,
+    record: &Record,
+    family: Family,
+) -> anyhow::Result<()> {
+    let record_type = family.record_type();
+    let policy = config.absent(family);
+
+    if is_withdrawn(config, cache, record, family) {
+        return Ok(());
+    }
+
+    if let Some(placeholder) = policy.placeholder() {
+        update_record(config, cloudflare, cache, zone_id, record, family, placeholder)?;
     } else {
-        let record = cloudflare
-            .fetch_dns_record(zone.id(), config.domain(), DnsRecordType::AAAA)
-            .context("failed to fetch DNS AAAA Record")?;
-        let ip = ip.v6().context("failed to fetch IPv6 address")?;
+        let dns_records = match cache.dns_records(zone_id, record.name(), record_type) {
+            Some(dns_records) => dns_records.to_vec(),
+            None => cloudflare
+                .fetch_dns_records(zone_id, record.name(), record_type)
+                .with_context(|| format!("failed to fetch DNS {} Records", record_type))?,
+        };
 
-        if record.content() == ip {
-            println!("AAAA Record already matches desired IPv6; skipping...");
-            None
-        } else if record.locked() {
-            println!("AAAA Record is locked; skipping...");
-            None
+        // the multiple policy chooses which records to update, but none of them should be left behind; if there are
+        // none, the record is already withdrawn
+        let indexes: Vec<_> = (0..dns_records.len()).collect();
+
+        let (deleted, failures) =
+            delete_dns_records(config, cloudflare, zone_id, record, record_type, &dns_records, &indexes);
+
+        if failures > 0 {
+            cache.remove_dns_records(zone_id, record.name(), record_type);
+            anyhow::bail!("failed to delete {} of the DNS {} Records", failures, record_type);
+        }
+
+        if !config.dry_run() {
+            let remaining =
+                dns_records.into_iter().filter(|dns_record| !deleted.iter().any(|id| id == dns_record.id()));
+            cache.set_dns_records(zone_id, record.name(), record_type, remaining.collect());
+        }
+    }
+
+    if !config.dry_run() {
+        println!(
+            "{}: {} Record withdrawn until there is an {} address ({})",
+            record.name(),
+            record_type,
+            family,
+            policy
+        );
+        cache.set_withdrawn(record.name(), record_type, policy.placeholder(), SystemTime::now());
+    }
+
+    Ok(())
+}
+
+/// Whether a record in a family without an address has already been withdrawn as the family's policy asks, or does
+/// not need to be.
+#[doc(hidden)]
+fn is_withdrawn(config: &Config, cache: &Cache, record: &Record, family: Family) -> bool {
+    let policy = config.absent(family);
+
+    *policy == AbsentPolicy::Keep
+        || cache
+            .withdrawal(record.name(), family.record_type())
+            .is_some_and(|withdrawal| withdrawal.parked() == policy.placeholder())
+}
+
+/// Forgets that a record was withdrawn (if it was), now that it points at an address again.
+#[doc(hidden)]
+fn restore_record(cache: &mut Cache, record: &Record, family: Family) {
+    if let Some(withdrawal) = cache.set_restored(record.name(), family.record_type()) {
+        let since = UNIX_EPOCH + Duration::from_secs(withdrawal.since());
+        println!(
+            "{}: {} Record restored; it was withdrawn at {}",
+            record.name(),
+            family.record_type(),
+            humantime::format_rfc3339_seconds(since)
+        );
+    }
+}
+
+/// Describes one of the DNS records for a name; by ID if there are several.
+#[doc(hidden)]
+fn label(dns_records: &[DnsRecord], index: usize, record_type: DnsRecordType) -> String {
+    if dns_records.len() > 1 {
+        format!("{} Record {}", record_type, dns_records[index].id())
+    } else {
+        format!("{} Record", record_type)
+    }
+}
+
+/// Deletes the DNS records at these indexes (unless they are locked), returning the IDs of those deleted and how many
+/// could not be.
+#[doc(hidden)]
+fn delete_dns_records(
+    config: &Config,
+    cloudflare: &cloudflare::Client,
+    zone_id: &str,
+    record: &Record,
+    record_type: DnsRecordType,
+    dns_records: &[DnsRecord],
+    indexes: &[usize],
+) -> (Vec<String>, usize) {
+    let mut deleted = Vec::new();
+    let mut failures = 0;
+
+    for &index in indexes {
+        let dns_record = &dns_records[index];
+        let label = label(dns_records, index, record_type);
+
+        if dns_record.locked() {
+            println!("{}: {} ({}) is locked; not deleting...", record.name(), label, dns_record.content());
+        } else if config.dry_run() {
+            println!("{}: {} ({}) would be deleted", record.name(), label, dns_record.content());
+        } else if let Err(e) = cloudflare.delete_dns_record(zone_id, dns_record.id()) {
+            eprintln!("{}: failed to delete DNS {}: {:#}", record.name(), label, e);
+            failures += 1;
         } else {
-            Some((record, ip))
+            println!("{}: {} ({}) deleted", record.name(), label, dns_record.content());
+            deleted.push(dns_record.id().to_string());
+        }
+    }
+
+    (deleted, failures)
+}
+
+/// Chooses which of the DNS records for a name should be updated, and which should be deleted, according to the
+/// record's [`MultiplePolicy`]; returned as indexes into `dns_records`.
+#[doc(hidden)]
+fn select_dns_records(
+    multiple: &MultiplePolicy,
+    dns_records: &[DnsRecord],
+    ip: IpAddr,
+) -> anyhow::Result<(Vec<usize>, Vec<usize>)> {
+    let all = (0..dns_records.len()).collect::<Vec<_>>();
+
+    let selected = match multiple {
+        MultiplePolicy::Fail if dns_records.len() > 1 => anyhow::bail!(
+            "{} records found; set `multiple` in the config (or pass --multiple) to choose how to handle them",
+            dns_records.len()
+        ),
+        MultiplePolicy::Fail | MultiplePolicy::All => all,
+        MultiplePolicy::Collapse => {
+            // prefer a record that already has the desired content, then one that can actually be updated
+            let keep = dns_records
+                .iter()
+                .position(|dns_record| dns_record.content() == ip)
+                .or_else(|| dns_records.iter().position(|dns_record| !dns_record.locked()))
+                .unwrap_or(0);
+
+            let extra = all.into_iter().filter(|index| *index != keep).collect();
+            return Ok((vec![keep], extra));
+        }
+        MultiplePolicy::Id(id) => all.into_iter().filter(|index| dns_records[*index].id() == id).collect(),
+        MultiplePolicy::Comment(comment) => {
+            all.into_iter().filter(|index| dns_records[*index].comment() == Some(comment.as_str())).collect()
         }
     };
 
-    if let Some((record, ip)) = ipv4 {
-        cloudflare
-            .update_dns_record(zone.id(), record.id(), IpAddr::V4(ip))
-            .context("failed to update DNS A Record")?;
+    if selected.is_empty() {
+        anyhow::bail!("none of the {} records match `{}`", dns_records.len(), multiple);
+    }
 
-        println!("A Record updated to: {}", ip);
+    Ok((selected, Vec::new()))
+}
+
+/// Brings a single DNS record up to date, returning a description of what was (or would have been) done.
+#[doc(hidden)]
+fn update_dns_record(
+    config: &Config,
+    cloudflare: &cloudflare::Client,
+    zone_id: &str,
+    record: &Record,
+    family: Family,
+    dns_record: &mut DnsRecord,
+    ip: IpAddr,
+) -> anyhow::Result<String> {
+    let (patch, changes) = drift(record, dns_record, ip);
+
+    if patch.is_empty() {
+        return Ok(format!("already matches desired {} ({}); skipping...", family, ip));
     }
 
-    if let Some((record, ip)) = ipv6 {
-        cloudflare
-            .update_dns_record(zone.id(), record.id(), IpAddr::V6(ip))
-            .context("failed to update DNS AAAA Record")?;
+    if dns_record.locked() {
+        return Ok(format!("is locked; skipping ({})...", changes.join(", ")));
+    }
 
-        println!("AAAA Record updated to: {}", ip);
+    if config.dry_run() {
+        return Ok(format!("would be updated: {}", changes.join(", ")));
     }
 
-    Ok(())
+    cloudflare.update_dns_record(zone_id, dns_record.id(), &patch)?;
+    dns_record.apply(&patch);
+
+    Ok(format!("updated: {}", changes.join(", ")))
+}
+
+/// The changes needed to bring a DNS record in line with the desired address and the record's configured settings,
+/// along with a description of each change; settings that are not configured are left as they are.
+#[doc(hidden)]
+fn drift(record: &Record, dns_record: &DnsRecord, ip: IpAddr) -> (DnsRecordPatch, Vec<String>) {
+    let mut patch = DnsRecordPatch::default();
+    let mut changes = Vec::new();
+
+    if dns_record.content() != ip {
+        changes.push(format!("content {} -> {}", dns_record.content(), ip));
+        patch.content = Some(ip);
+    }
+
+    if let Some(proxied) = record.proxied().filter(|proxied| *proxied != dns_record.proxied()) {
+        changes.push(format!("proxied {} -> {}", dns_record.proxied(), proxied));
+        patch.proxied = Some(proxied);
+    }
+
+    // proxied records always have an automatic TTL, so a configured TTL only applies while the record is not proxied
+    let proxied = record.proxied().unwrap_or_else(|| dns_record.proxied());
+    if let Some(ttl) = record.ttl().filter(|ttl| !proxied && *ttl != dns_record.ttl()) {
+        changes.push(format!("ttl {} -> {}", dns_record.ttl(), ttl));
+        patch.ttl = Some(ttl);
+    }
+
+    if let Some(comment) = record.comment().filter(|comment| *comment != dns_record.comment().unwrap_or_default()) {
+        changes.push(format!("comment {:?} -> {:?}", dns_record.comment().unwrap_or_default(), comment));
+        patch.comment = Some(comment.to_string());
+    }
+
+    if let Some(tags) = record.tags() {
+        let mut desired = tags.to_vec();
+        let mut current = dns_record.tags().to_vec();
+        desired.sort();
+        current.sort();
+
+        if desired != current {
+            changes.push(format!("tags {:?} -> {:?}", current, desired));
+            patch.tags = Some(desired);
+        }
+    }
+
+    (patch, changes)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::api::cloudflare::tests::{mock_dns_record, mock_dns_record_update, mock_zone};
-    use crate::api::ip::tests::{mock_v4, mock_v6};
+    use crate::api::cloudflare::tests::{
+        mock_dns_record, mock_dns_record_create, mock_dns_record_delete, mock_dns_record_empty, mock_dns_record_update,
+        mock_dns_records_multiple, mock_zone, mock_zones,
+    };
+    use crate::api::cloudflare::{DnsRecord, DnsRecordPatch, DnsRecordType};
+    use crate::api::ip::tests::{
+        mock_command, mock_dns, mock_fritzbox_v4, mock_fritzbox_v6, mock_interfaces, mock_interfaces_ula_only,
+        mock_interfaces_v4_only, mock_router, mock_stun, mock_trace, mock_v4, mock_v6,
+    };
+    use crate::api::ip::InterfaceAddress;
     use crate::api::{cloudflare, ip};
-    use crate::config::Config;
-    use crate::update;
+    use crate::cache::{Cache, Withdrawal};
+    use crate::config::{Args, Config, Family, Record};
+    use crate::summary::Summary;
+    use crate::{drift, publish, update, withdraw_record};
+    use std::net::{IpAddr, Ipv4Addr};
+    use ureq::serde_json::Value;
+    use ureq::{Request, Response};
 
     // Not an actual token; taken directly from the API documentation
     const API_TOKEN: &str = "YQSn-xWAQiiEh9qM58wZNnyQS7FUdoqGIUAbrh7T";
 
+    /// The mocks only respond with documentation addresses, which would otherwise be refused.
+    const ALLOW_DOCUMENTATION: [&str; 6] = [
+        "--allow-reserved",
+        "198.51.100.0/24",
+        "--allow-reserved",
+        "203.0.113.0/24",
+        "--allow-reserved",
+        "2001:db8::/32",
+    ];
+
+    fn load(args: &[&str]) -> anyhow::Result<Config> {
+        let args: Vec<_> = args.iter().chain(ALLOW_DOCUMENTATION.iter()).copied().collect();
+        let args: Args =
+            argh::FromArgs::from_args(&["ddns-for-cloudflare"], &args).map_err(|e| anyhow::anyhow!("{:?}", e))?;
+
+        Config::load(&args)
+    }
+
     #[test]
     fn update_mocked() -> anyhow::Result<()> {
         let config = Config::new("example.com", "example.com", API_TOKEN, false, false);
 
-        let mut cloudflare = cloudflare::Client::new(config.api_token());
-        let mut ip = ip::Client::new();
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
 
         cloudflare.set_get_zone(mock_zone);
         cloudflare.set_get_dns_record(mock_dns_record);
@@ -123,15 +707,147 @@ mod tests {
         ip.set_fetch_v4(mock_v4);
         ip.set_fetch_v6(mock_v6);
 
-        update(&config, cloudflare, ip)
+        update(&config, &cloudflare, &ip, &mut Cache::default())
+    }
+
+    fn mock_unreachable_patch(_: Request, _: Value) -> Result<Response, ureq::Error> {
+        panic!("no DNS Record should have been updated");
+    }
+
+    #[test]
+    fn update_mocked_derived_zone() -> anyhow::Result<()> {
+        let config = load(&["--domain", "a.b.example.co.uk", "--api-token", API_TOKEN])?;
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+        let mut cache = Cache::default();
+
+        cloudflare.set_get_zone(mock_zones);
+        cloudflare.set_get_dns_record(mock_dns_record);
+        cloudflare.set_patch_dns_record(mock_dns_record_update);
+
+        ip.set_fetch_v4(mock_v4);
+        ip.set_fetch_v6(mock_v6);
+
+        update(&config, &cloudflare, &ip, &mut cache)?;
+        assert_eq!(cache.zone_id("b.example.co.uk"), Some("5d8e3f2a1b0c9d8e7f6a5b4c3d2e1f0a"));
+
+        let config = load(&["--domain", "example.org", "--api-token", API_TOKEN])?;
+
+        assert!(update(&config, &cloudflare, &ip, &mut cache).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_mocked_zone_id() -> anyhow::Result<()> {
+        let config = load(&[
+            "--zone-id",
+            "023e105f4ecef8ad9ca31a8372d0c353",
+            "--domain",
+            "example.com",
+            "--api-token",
+            API_TOKEN,
+        ])?;
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+
+        cloudflare.set_get_zone(mock_unreachable);
+        cloudflare.set_get_dns_record(mock_dns_record);
+        cloudflare.set_patch_dns_record(mock_dns_record_update);
+
+        ip.set_fetch_v4(mock_v4);
+        ip.set_fetch_v6(mock_v6);
+
+        update(&config, &cloudflare, &ip, &mut Cache::default())
+    }
+
+    #[test]
+    fn update_mocked_dry_run() -> anyhow::Result<()> {
+        let config =
+            load(&["--zone", "example.com", "--domain", "example.com", "--api-token", API_TOKEN, "--dry-run"])?;
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+
+        cloudflare.set_get_zone(mock_zone);
+        cloudflare.set_get_dns_record(mock_dns_record);
+        cloudflare.set_patch_dns_record(mock_unreachable_patch);
+
+        ip.set_fetch_v4(mock_v4);
+        ip.set_fetch_v6(mock_v6);
+
+        update(&config, &cloudflare, &ip, &mut Cache::default())
+    }
+
+    #[test]
+    fn update_mocked_create_missing() -> anyhow::Result<()> {
+        let config =
+            load(&["--zone", "example.com", "--domain", "example.com", "--api-token", API_TOKEN, "--create-missing"])?;
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+
+        cloudflare.set_get_zone(mock_zone);
+        cloudflare.set_get_dns_record(mock_dns_record_empty);
+        cloudflare.set_patch_dns_record(mock_unreachable_patch);
+        cloudflare.set_post_dns_record(mock_dns_record_create);
+
+        ip.set_fetch_v4(mock_v4);
+        ip.set_fetch_v6(mock_v6);
+
+        update(&config, &cloudflare, &ip, &mut Cache::default())?;
+
+        let config = Config::new("example.com", "example.com", API_TOKEN, false, false);
+        assert!(update(&config, &cloudflare, &ip, &mut Cache::default()).is_err());
+
+        Ok(())
+    }
+
+    fn update_mocked_multiple(multiple: &str) -> anyhow::Result<()> {
+        let config = load(&[
+            "--zone",
+            "example.com",
+            "--domain",
+            "example.com",
+            "--api-token",
+            API_TOKEN,
+            "--multiple",
+            multiple,
+        ])?;
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+
+        cloudflare.set_get_zone(mock_zone);
+        cloudflare.set_get_dns_record(mock_dns_records_multiple);
+        cloudflare.set_patch_dns_record(mock_dns_record_update);
+        cloudflare.set_delete_dns_record(mock_dns_record_delete);
+
+        ip.set_fetch_v4(mock_v4);
+        ip.set_fetch_v6(mock_v6);
+
+        update(&config, &cloudflare, &ip, &mut Cache::default())
+    }
+
+    #[test]
+    fn update_mocked_multiple_policies() -> anyhow::Result<()> {
+        assert!(update_mocked_multiple("fail").is_err());
+        assert!(update_mocked_multiple("id:0123456789abcdef0123456789abcdef").is_err());
+
+        update_mocked_multiple("all")?;
+        update_mocked_multiple("collapse")?;
+        update_mocked_multiple("id:372e67954025e0ba6aaa6d586b9e0b59")?;
+        update_mocked_multiple("comment:primary")
     }
 
     #[test]
     fn update_mocked_v4_only() -> anyhow::Result<()> {
         let config = Config::new("example.com", "example.com", API_TOKEN, true, false);
 
-        let mut cloudflare = cloudflare::Client::new(config.api_token());
-        let mut ip = ip::Client::new();
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
 
         cloudflare.set_get_zone(mock_zone);
         cloudflare.set_get_dns_record(mock_dns_record);
@@ -140,15 +856,15 @@ mod tests {
         ip.set_fetch_v4(mock_v4);
         ip.set_fetch_v6(mock_v6);
 
-        update(&config, cloudflare, ip)
+        update(&config, &cloudflare, &ip, &mut Cache::default())
     }
 
     #[test]
     fn update_mocked_v6_only() -> anyhow::Result<()> {
         let config = Config::new("example.com", "example.com", API_TOKEN, false, true);
 
-        let mut cloudflare = cloudflare::Client::new(config.api_token());
-        let mut ip = ip::Client::new();
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
 
         cloudflare.set_get_zone(mock_zone);
         cloudflare.set_get_dns_record(mock_dns_record);
@@ -157,6 +873,584 @@ mod tests {
         ip.set_fetch_v4(mock_v4);
         ip.set_fetch_v6(mock_v6);
 
-        update(&config, cloudflare, ip)
+        update(&config, &cloudflare, &ip, &mut Cache::default())
+    }
+
+    fn mock_v6_failing(_: Request) -> Result<Response, ureq::Error> {
+        Response::new(503, "Service Unavailable", "")
+    }
+
+    /// Updates both records while IPv6 discovery fails, returning how the update went and the state it left.
+    fn update_mocked_without_v6(
+        mode: &str,
+        interfaces: fn() -> anyhow::Result<Vec<InterfaceAddress>>,
+    ) -> anyhow::Result<(anyhow::Result<()>, Cache)> {
+        let config =
+            load(&["--zone", "example.com", "--domain", "example.com", "--api-token", API_TOKEN, "--ipv6-mode", mode])?;
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+        let mut cache = Cache::default();
+
+        cloudflare.set_get_zone(mock_zone);
+        cloudflare.set_get_dns_record(mock_dns_record);
+        cloudflare.set_patch_dns_record(mock_dns_record_update);
+
+        ip.set_fetch_v4(mock_v4);
+        ip.set_fetch_v6(mock_v6_failing);
+        ip.set_list_interfaces(interfaces);
+
+        let result = update(&config, &cloudflare, &ip, &mut cache);
+        Ok((result, cache))
+    }
+
+    #[test]
+    fn update_mocked_family_modes() -> anyhow::Result<()> {
+        let ipv4 = Some(Ipv4Addr::new(203, 0, 113, 4));
+        let cases = [
+            ("required", mock_interfaces_v4_only as fn() -> _, true),
+            ("optional", mock_interfaces, false),
+            // skipped quietly only when the host has no global IPv6 address
+            ("auto", mock_interfaces, true),
+            ("auto", mock_interfaces_v4_only, false),
+            ("auto", mock_interfaces_ula_only, false),
+        ];
+
+        for (mode, interfaces, fails) in cases {
+            let (result, cache) = update_mocked_without_v6(mode, interfaces)?;
+
+            // the A record is updated either way
+            assert_eq!(result.is_err(), fails, "--ipv6-mode {} failed: {:?}", mode, result);
+            assert!(cache.is_published(ipv4, None));
+            assert!(!cache.is_published(None, Some("2001:db8::1".parse()?)));
+        }
+
+        Ok(())
+    }
+
+    /// Deletes whichever DNS record was asked for, as the ID in the response has to match it.
+    #[allow(clippy::needless_pass_by_value)] // must match the signature of the real request
+    fn mock_dns_record_delete_any(request: Request) -> Result<Response, ureq::Error> {
+        let id = request.url().rsplit('/').next().unwrap_or_default();
+
+        Response::new(200, "OK", &format!(r#"{{"success": true, "errors": [], "result": {{"id": "{}"}}}}"#, id))
+    }
+
+    #[test]
+    fn update_mocked_withdrawn() -> anyhow::Result<()> {
+        let args =
+            ["--zone", "example.com", "--domain", "example.com", "--api-token", API_TOKEN, "--ipv6-mode", "auto"];
+        let config = load(&[&args[..], &["--ipv6-absent", "delete"]].concat())?;
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+        let mut cache = Cache::default();
+
+        cloudflare.set_get_zone(mock_zone);
+        cloudflare.set_get_dns_record(mock_dns_record);
+        cloudflare.set_patch_dns_record(mock_dns_record_update);
+        cloudflare.set_delete_dns_record(mock_dns_record_delete_any);
+
+        ip.set_fetch_v4(mock_v4);
+        ip.set_fetch_v6(mock_v6);
+        ip.set_list_interfaces(mock_interfaces_v4_only);
+
+        // the AAAA record is deleted once IPv6 connectivity is gone, and left alone after that
+        update(&config, &cloudflare, &ip, &mut cache)?;
+        assert_eq!(cache.withdrawal("example.com", DnsRecordType::AAAA).map(Withdrawal::parked), Some(None));
+
+        cloudflare.set_delete_dns_record(mock_unreachable);
+        update(&config, &cloudflare, &ip, &mut cache)?;
+
+        // and created again once it is back, without --create-missing
+        cloudflare.set_post_dns_record(mock_dns_record_create);
+        ip.set_list_interfaces(mock_interfaces);
+        update(&config, &cloudflare, &ip, &mut cache)?;
+        assert_eq!(cache.withdrawal("example.com", DnsRecordType::AAAA), None);
+        assert!(cache.is_published(Some(Ipv4Addr::new(203, 0, 113, 4)), Some("2001:db8::1".parse()?)));
+
+        let placeholder = "2001:db8::dead".parse()?;
+        let config = load(&[&args[..], &["--ipv6-absent", "park:2001:db8::dead"]].concat())?;
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+        ip.set_fetch_v4(mock_v4);
+        ip.set_list_interfaces(mock_interfaces_v4_only);
+
+        update(&config, &cloudflare, &ip, &mut cache)?;
+        assert_eq!(
+            cache.withdrawal("example.com", DnsRecordType::AAAA).map(Withdrawal::parked),
+            Some(Some(placeholder))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_mocked_withdrawn_failed() -> anyhow::Result<()> {
+        let config = load(&[
+            "--zone",
+            "example.com",
+            "--domain",
+            "example.com",
+            "--api-token",
+            API_TOKEN,
+            "--ipv6-mode",
+            "auto",
+            "--ipv6-absent",
+            "delete",
+        ])?;
+
+        // neither a failed discovery nor a refused address means the host has no IPv6 address; the interfaces say it
+        // does, so the failure fails the run instead
+        for fetch_v6 in [mock_v6_failing as fn(_) -> _, mock_v6_unique_local] {
+            let mut cloudflare = cloudflare::Client::new(config.authentication());
+            let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+            let mut cache = Cache::default();
+
+            cloudflare.set_get_zone(mock_zone);
+            cloudflare.set_get_dns_record(mock_dns_record);
+            cloudflare.set_patch_dns_record(mock_dns_record_update);
+            cloudflare.set_delete_dns_record(mock_unreachable);
+
+            ip.set_fetch_v4(mock_v4);
+            ip.set_fetch_v6(fetch_v6);
+            ip.set_list_interfaces(mock_interfaces);
+
+            assert!(update(&config, &cloudflare, &ip, &mut cache).is_err());
+            assert_eq!(cache.withdrawal("example.com", DnsRecordType::AAAA), None);
+        }
+
+        Ok(())
+    }
+
+    fn mock_v6_unique_local(_: Request) -> Result<Response, ureq::Error> {
+        Response::new(200, "OK", "fd00::1")
+    }
+
+    #[test]
+    fn withdraw_record_multiple() -> anyhow::Result<()> {
+        const ZONE_ID: &str = "023e105f4ecef8ad9ca31a8372d0c353";
+
+        // every record is deleted, whichever of them the multiple policy would update
+        for multiple in ["fail", "id:372e67954025e0ba6aaa6d586b9e0b59", "comment:primary"] {
+            let config = load(&[
+                "--zone-id",
+                ZONE_ID,
+                "--domain",
+                "example.com",
+                "--api-token",
+                API_TOKEN,
+                "--multiple",
+                multiple,
+                "--ipv4-mode",
+                "auto",
+                "--ipv4-absent",
+                "delete",
+            ])?;
+            let record = &config.zones()[0].records()[0];
+
+            let mut cloudflare = cloudflare::Client::new(config.authentication());
+            let mut cache = Cache::default();
+
+            cloudflare.set_get_dns_record(mock_dns_records_multiple);
+            cloudflare.set_delete_dns_record(mock_dns_record_delete_any);
+
+            withdraw_record(&config, &cloudflare, &mut cache, ZONE_ID, record, Family::V4)?;
+            assert_eq!(cache.dns_records(ZONE_ID, "example.com", DnsRecordType::A), Some(&[][..]));
+            assert_eq!(cache.withdrawal("example.com", DnsRecordType::A).map(Withdrawal::parked), Some(None));
+
+            // with no records left, there is nothing more to do
+            let mut cache = Cache::default();
+            cloudflare.set_get_dns_record(mock_dns_record_empty);
+            cloudflare.set_delete_dns_record(mock_unreachable);
+
+            withdraw_record(&config, &cloudflare, &mut cache, ZONE_ID, record, Family::V4)?;
+            assert_eq!(cache.withdrawal("example.com", DnsRecordType::A).map(Withdrawal::parked), Some(None));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_mocked_config() -> anyhow::Result<()> {
+        // only the first IPv4 provider in the config understands the mocked response
+        let config = load(&["--config", "resources/tests/config/config.toml", "--ipv4-quorum", "1"])?;
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+
+        cloudflare.set_get_zone(mock_zones);
+        cloudflare.set_get_dns_record(mock_dns_record);
+        cloudflare.set_patch_dns_record(mock_dns_record_update);
+
+        ip.set_fetch_v4(mock_trace);
+        ip.set_list_interfaces(mock_interfaces);
+
+        update(&config, &cloudflare, &ip, &mut Cache::default())
+    }
+
+    fn mock_v6_delegated(_: Request) -> Result<Response, ureq::Error> {
+        Response::new(200, "OK", "2001:db8:aa:bb::4")
+    }
+
+    fn mock_dns_record_update_interface_id(request: Request, body: Value) -> Result<Response, ureq::Error> {
+        assert!(["\"2001:db8:aa::10\"", "\"2001:db8:aa:bb::20\""].contains(&body["content"].to_string().as_str()));
+
+        mock_dns_record_update(request, body)
+    }
+
+    #[test]
+    fn update_mocked_interface_id() -> anyhow::Result<()> {
+        let config = load(&["--config", "resources/tests/config/interface_id.toml"])?;
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+
+        cloudflare.set_get_zone(mock_zone);
+        cloudflare.set_get_dns_record(mock_dns_record);
+        cloudflare.set_patch_dns_record(mock_dns_record_update_interface_id);
+
+        ip.set_fetch_v4(mock_unreachable);
+        ip.set_fetch_v6(mock_v6_delegated);
+
+        update(&config, &cloudflare, &ip, &mut Cache::default())
+    }
+
+    fn mock_unreachable(_: Request) -> Result<Response, ureq::Error> {
+        panic!("cached value should have been used instead of calling the API");
+    }
+
+    #[test]
+    fn update_mocked_cached() -> anyhow::Result<()> {
+        let config = Config::new("example.com", "example.com", API_TOKEN, false, false);
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+        let mut cache = Cache::default();
+
+        cloudflare.set_get_zone(mock_zone);
+        cloudflare.set_get_dns_record(mock_dns_record);
+        cloudflare.set_patch_dns_record(mock_dns_record_update);
+
+        ip.set_fetch_v4(mock_v4);
+        ip.set_fetch_v6(mock_v6);
+
+        update(&config, &cloudflare, &ip, &mut cache)?;
+
+        cloudflare.set_get_zone(mock_unreachable);
+        cloudflare.set_get_dns_record(mock_unreachable);
+
+        update(&config, &cloudflare, &ip, &mut cache)
+    }
+
+    fn mock_loopback(_: Request) -> Result<Response, ureq::Error> {
+        Response::new(200, "OK", "127.0.0.1")
+    }
+
+    fn mock_carrier_grade_nat(_: Request) -> Result<Response, ureq::Error> {
+        Response::new(200, "OK", "100.64.0.1")
+    }
+
+    #[test]
+    fn update_mocked_reserved() -> anyhow::Result<()> {
+        let args: Args = argh::FromArgs::from_args(
+            &["ddns-for-cloudflare"],
+            &["--zone", "example.com", "--domain", "example.com", "--api-token", API_TOKEN, "--only-v4"],
+        )
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+        let config = Config::load(&args)?;
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+
+        cloudflare.set_get_zone(mock_unreachable);
+        cloudflare.set_get_dns_record(mock_unreachable);
+
+        ip.set_fetch_v4(mock_loopback);
+        assert!(update(&config, &cloudflare, &ip, &mut Cache::default()).is_err());
+
+        ip.set_fetch_v4(mock_carrier_grade_nat);
+        assert!(update(&config, &cloudflare, &ip, &mut Cache::default()).is_err());
+
+        let config =
+            load(&["--zone", "example.com", "--domain", "example.com", "--api-token", API_TOKEN, "--only-v4"])?;
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+
+        cloudflare.set_get_zone(mock_unreachable);
+        cloudflare.set_get_dns_record(mock_unreachable);
+
+        ip.set_fetch_v4(mock_loopback);
+        assert!(update(&config, &cloudflare, &ip, &mut Cache::default()).is_err());
+
+        let config = load(&[
+            "--zone",
+            "example.com",
+            "--domain",
+            "example.com",
+            "--api-token",
+            API_TOKEN,
+            "--only-v4",
+            "--allow-reserved",
+            "100.64.0.0/10",
+        ])?;
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+
+        cloudflare.set_get_zone(mock_zone);
+        cloudflare.set_get_dns_record(mock_dns_record);
+        cloudflare.set_patch_dns_record(mock_dns_record_update);
+
+        ip.set_fetch_v4(mock_carrier_grade_nat);
+        update(&config, &cloudflare, &ip, &mut Cache::default())
+    }
+
+    #[test]
+    fn update_mocked_command() -> anyhow::Result<()> {
+        let config = load(&[
+            "--zone",
+            "example.com",
+            "--domain",
+            "example.com",
+            "--api-token",
+            API_TOKEN,
+            "--ipv4-command",
+            "203.0.113.5 # e.g. an SNMP query",
+            "--ipv6",
+            "2001:db8::5",
+        ])?;
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+
+        cloudflare.set_get_zone(mock_zone);
+        cloudflare.set_get_dns_record(mock_dns_record);
+        cloudflare.set_patch_dns_record(mock_dns_record_update);
+
+        ip.set_fetch_v4(mock_unreachable);
+        ip.set_fetch_v6(mock_unreachable);
+        ip.set_run_command(mock_command);
+
+        update(&config, &cloudflare, &ip, &mut Cache::default())
+    }
+
+    #[test]
+    fn update_mocked_stun_and_dns() -> anyhow::Result<()> {
+        let config = load(&[
+            "--zone",
+            "example.com",
+            "--domain",
+            "example.com",
+            "--api-token",
+            API_TOKEN,
+            "--ipv4-stun",
+            "failing.example",
+            "--ipv4-stun",
+            "stun.example:3478",
+            "--ipv6-dns",
+            "whoami.example/txt/ch@resolver.example",
+        ])?;
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+
+        cloudflare.set_get_zone(mock_zone);
+        cloudflare.set_get_dns_record(mock_dns_record);
+        cloudflare.set_patch_dns_record(mock_dns_record_update);
+
+        ip.set_fetch_v4(mock_unreachable);
+        ip.set_fetch_v6(mock_unreachable);
+        ip.set_query_stun(mock_stun);
+        ip.set_query_dns(mock_dns);
+
+        update(&config, &cloudflare, &ip, &mut Cache::default())
+    }
+
+    #[test]
+    fn update_mocked_router() -> anyhow::Result<()> {
+        let config = load(&[
+            "--zone",
+            "example.com",
+            "--domain",
+            "example.com",
+            "--api-token",
+            API_TOKEN,
+            "--only-v4",
+            "--ipv4-router",
+            "auto",
+        ])?;
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+
+        cloudflare.set_get_zone(mock_zone);
+        cloudflare.set_get_dns_record(mock_dns_record);
+        cloudflare.set_patch_dns_record(mock_dns_record_update);
+
+        ip.set_fetch_v4(mock_unreachable);
+        ip.set_query_router(mock_router);
+
+        update(&config, &cloudflare, &ip, &mut Cache::default())
+    }
+
+    #[test]
+    fn update_mocked_fritzbox() -> anyhow::Result<()> {
+        let config = load(&[
+            "--zone",
+            "example.com",
+            "--domain",
+            "example.com",
+            "--api-token",
+            API_TOKEN,
+            "--ipv4-fritzbox",
+            "http://192.0.2.1:49000",
+            "--ipv6-fritzbox",
+            "http://192.0.2.1:49000",
+        ])?;
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+
+        cloudflare.set_get_zone(mock_zone);
+        cloudflare.set_get_dns_record(mock_dns_record);
+        cloudflare.set_patch_dns_record(mock_dns_record_update);
+
+        ip.set_fetch_v4(mock_unreachable);
+        ip.set_fetch_v6(mock_unreachable);
+        ip.set_query_fritzbox_v4(mock_fritzbox_v4);
+        ip.set_query_fritzbox_v6(mock_fritzbox_v6);
+
+        update(&config, &cloudflare, &ip, &mut Cache::default())
+    }
+
+    #[test]
+    fn publish_mocked_hook() -> anyhow::Result<()> {
+        let mut config = load(&["--zone", "example.com", "--domain", "example.com", "--api-token", API_TOKEN])?;
+        config.retain_families(&[Family::V4]);
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut cache = Cache::default();
+
+        cloudflare.set_get_zone(mock_zone);
+        cloudflare.set_get_dns_record(mock_dns_record);
+        cloudflare.set_patch_dns_record(mock_dns_record_update);
+
+        let ipv4 = Some(Ipv4Addr::new(198, 51, 100, 9));
+        publish(&config, &cloudflare, &mut cache, ipv4, None, Summary::default())?;
+        assert!(cache.is_published(ipv4, None));
+
+        // without the restriction, the AAAA record has no address to be published with, so it is left alone
+        let config = load(&["--zone", "example.com", "--domain", "example.com", "--api-token", API_TOKEN])?;
+        let mut cache = Cache::default();
+        publish(&config, &cloudflare, &mut cache, ipv4, None, Summary::default())?;
+        assert!(cache.is_published(ipv4, None));
+
+        Ok(())
+    }
+
+    fn mock_v4_changed(_: Request) -> Result<Response, ureq::Error> {
+        Response::new(200, "OK", "198.51.100.7")
+    }
+
+    #[test]
+    fn update_mocked_published() -> anyhow::Result<()> {
+        let config = Config::new("example.com", "example.com", API_TOKEN, true, false);
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+        let mut cache = Cache::default();
+
+        cloudflare.set_get_zone(mock_zone);
+        cloudflare.set_get_dns_record(mock_dns_record);
+        cloudflare.set_patch_dns_record(mock_dns_record_update);
+
+        ip.set_fetch_v4(mock_v4);
+
+        update(&config, &cloudflare, &ip, &mut cache)?;
+
+        // unchanged, so the API is not called at all
+        cloudflare.set_get_zone(mock_unreachable);
+        cloudflare.set_get_dns_record(mock_unreachable);
+        cloudflare.set_patch_dns_record(mock_unreachable_patch);
+
+        update(&config, &cloudflare, &ip, &mut cache)?;
+
+        // changed, so the cached record is updated without looking it up again
+        ip.set_fetch_v4(mock_v4_changed);
+        cloudflare.set_patch_dns_record(mock_dns_record_update);
+
+        update(&config, &cloudflare, &ip, &mut cache)?;
+        assert!(cache.is_published(Some(Ipv4Addr::new(198, 51, 100, 7)), None));
+
+        // forced, so everything is looked up again
+        cache.clear();
+        cloudflare.set_get_zone(mock_zone);
+        cloudflare.set_get_dns_record(mock_dns_record);
+
+        update(&config, &cloudflare, &ip, &mut cache)
+    }
+
+    #[test]
+    fn update_mocked_interface() -> anyhow::Result<()> {
+        let config = load(&[
+            "--zone",
+            "example.com",
+            "--domain",
+            "example.com",
+            "--api-token",
+            API_TOKEN,
+            "--ipv6-interface",
+            "eth0",
+            "--ipv6-prefix",
+            "2001:db8::/32",
+        ])?;
+
+        let mut cloudflare = cloudflare::Client::new(config.authentication());
+        let mut ip = ip::Client::new(config.ipv4(), config.ipv6());
+
+        cloudflare.set_get_zone(mock_zone);
+        cloudflare.set_get_dns_record(mock_dns_record);
+        cloudflare.set_patch_dns_record(mock_dns_record_update);
+
+        ip.set_fetch_v4(mock_v4);
+        ip.set_fetch_v6(mock_unreachable);
+        ip.set_list_interfaces(mock_interfaces);
+
+        update(&config, &cloudflare, &ip, &mut Cache::default())
+    }
+
+    #[test]
+    fn drift_settings() -> anyhow::Result<()> {
+        let config = load(&[
+            "--config",
+            "resources/tests/config/config.toml",
+            "--proxied",
+            "false",
+            "--ttl",
+            "300",
+            "--tag",
+            "ddns:true",
+        ])?;
+        let record = &config.zones()[0].records()[1];
+
+        let ip = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 4));
+        let dns_record = DnsRecord::new("372e67954025e0ba6aaa6d586b9e0b59", false, ip, 1, true, None);
+
+        let (patch, changes) = drift(record, &dns_record, ip);
+        assert_eq!(
+            patch,
+            DnsRecordPatch {
+                ttl: Some(300),
+                proxied: Some(false),
+                comment: Some("managed by ddns-for-cloudflare".into()),
+                tags: Some(vec!["ddns:true".into()]),
+                ..DnsRecordPatch::default()
+            }
+        );
+        assert_eq!(changes.len(), 4);
+
+        let (patch, _) = drift(&Record::new("example.com"), &dns_record, ip);
+        assert!(patch.is_empty());
+
+        Ok(())
     }
 }
@@ -0,0 +1,141 @@
+use crate::config::{Family, FamilyMode};
+use std::net::IpAddr;
+
+/// What became of an address family in one update.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    /// Every record in the family now points at the address.
+    Published(IpAddr),
+    /// The address has not changed since it was last published, so the records were left alone.
+    Unchanged(IpAddr),
+    /// The host has no global address in the family, so it was not discovered at all.
+    Skipped,
+    /// The address could not be discovered, or may not be published.
+    Failed(String),
+    /// This many records could not be pointed at the address; why is on stderr.
+    FailedRecords(IpAddr, usize),
+    /// There was no address, and this many records could not be withdrawn; why is on stderr.
+    FailedWithdrawals(usize),
+}
+
+impl Outcome {
+    pub const fn is_failure(&self) -> bool {
+        matches!(self, Self::Failed(_) | Self::FailedRecords(..) | Self::FailedWithdrawals(_))
+    }
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Published(ip) => write!(f, "published {}", ip),
+            Self::Unchanged(ip) => write!(f, "unchanged at {}", ip),
+            Self::Skipped => write!(f, "skipped; no network interface has a global address"),
+            Self::Failed(reason) => write!(f, "failed; {}", reason),
+            Self::FailedRecords(ip, failures) => write!(f, "failed to point {} DNS Record(s) at {}", failures, ip),
+            Self::FailedWithdrawals(failures) => {
+                write!(f, "no address, and failed to withdraw {} DNS Record(s)", failures)
+            }
+        }
+    }
+}
+
+/// The outcome of each address family in one update, so that a failure in one family does not stop the other from
+/// being updated.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Summary {
+    ipv4: Option<Outcome>,
+    ipv6: Option<Outcome>,
+}
+
+impl Summary {
+    pub const fn outcome(&self, family: Family) -> Option<&Outcome> {
+        match family {
+            Family::V4 => self.ipv4.as_ref(),
+            Family::V6 => self.ipv6.as_ref(),
+        }
+    }
+
+    pub fn set(&mut self, family: Family, outcome: Outcome) {
+        match family {
+            Family::V4 => self.ipv4 = Some(outcome),
+            Family::V6 => self.ipv6 = Some(outcome),
+        }
+    }
+
+    /// Counts a record that could not be pointed at the family's address.
+    pub fn fail_record(&mut self, family: Family, ip: IpAddr) {
+        let failures = match self.outcome(family) {
+            Some(Outcome::FailedRecords(_, failures)) => failures + 1,
+            _ => 1,
+        };
+
+        self.set(family, Outcome::FailedRecords(ip, failures));
+    }
+
+    /// Counts a record that could not be withdrawn while the family had no address.
+    pub fn fail_withdrawal(&mut self, family: Family) {
+        let failures = match self.outcome(family) {
+            Some(Outcome::FailedWithdrawals(failures)) => failures + 1,
+            _ => 1,
+        };
+
+        self.set(family, Outcome::FailedWithdrawals(failures));
+    }
+
+    pub fn is_failed(&self, family: Family) -> bool {
+        self.outcome(family).is_some_and(Outcome::is_failure)
+    }
+
+    /// Prints the outcome of each family, failing if a family whose mode is not optional failed.
+    pub fn finish(&self, mode: impl Fn(Family) -> FamilyMode) -> anyhow::Result<()> {
+        let mut failed = Vec::new();
+
+        for family in [Family::V4, Family::V6] {
+            match self.outcome(family) {
+                None => {}
+                Some(outcome) if outcome.is_failure() => {
+                    eprintln!("{}: {}", family, outcome);
+
+                    // an auto family only gets this far if the host has an address in it
+                    if mode(family) != FamilyMode::Optional {
+                        failed.push(family.to_string());
+                    }
+                }
+                Some(outcome) => println!("{}: {}", family, outcome),
+            }
+        }
+
+        if !failed.is_empty() {
+            anyhow::bail!("failed to update {}; see stderr for details", failed.join(" and "));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{Family, FamilyMode};
+    use crate::summary::{Outcome, Summary};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn finish() {
+        let ipv6 = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+        let mut summary = Summary::default();
+        summary.set(Family::V4, Outcome::Published(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 4))));
+        assert!(summary.finish(|_| FamilyMode::Required).is_ok());
+
+        summary.fail_record(Family::V6, ipv6);
+        summary.fail_record(Family::V6, ipv6);
+        assert_eq!(summary.outcome(Family::V6), Some(&Outcome::FailedRecords(ipv6, 2)));
+        assert!(summary.is_failed(Family::V6));
+        assert!(!summary.is_failed(Family::V4));
+
+        assert!(summary.finish(|_| FamilyMode::Required).is_err());
+        assert!(summary.finish(|_| FamilyMode::Auto).is_err());
+        assert!(summary
+            .finish(|family| if family == Family::V6 { FamilyMode::Optional } else { FamilyMode::Required })
+            .is_ok());
+    }
+}